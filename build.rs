@@ -5,7 +5,7 @@ fn main() -> anyhow::Result<()> {
     protobuf_codegen::Codegen::new()
         .pure()
         .out_dir("src/generated")
-        .inputs(&["proto/api.proto"])
+        .inputs(["proto/api.proto"])
         .include("proto")
         .run()?;
     Ok(())
@@ -0,0 +1,25 @@
+use iterm2_api::ITerm2Connection;
+
+/// Usage: cargo run --example capture_workspace -- <output.toml|yaml>
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let path = std::env::args()
+        .nth(1)
+        .ok_or("Usage: capture_workspace <file>")?;
+
+    println!("Connecting to iTerm2...");
+    let mut connection = ITerm2Connection::connect().await?;
+
+    println!("Capturing current windows...");
+    let workspace = connection.capture_workspace().await?;
+    workspace.save(&path)?;
+
+    println!(
+        "Saved {} windows to {}. Recreate them with:",
+        workspace.windows.len(),
+        path
+    );
+    println!("  cargo run --example workspace -- {path}");
+
+    Ok(())
+}
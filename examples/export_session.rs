@@ -0,0 +1,30 @@
+use iterm2_api::ITerm2Connection;
+use iterm2_api::buffer::BufferRange;
+use iterm2_api::export::ExportFormat;
+
+/// Usage: cargo run --example export_session -- <output.html|ans|svg> [lines]
+///
+/// Exports the active session's last `lines` lines (default: the screen).
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let path = args.next().ok_or("Usage: export_session <file> [lines]")?;
+    let range = match args.next() {
+        Some(lines) => BufferRange::Trailing(lines.parse()?),
+        None => BufferRange::Screen,
+    };
+    let format = match path.rsplit('.').next() {
+        Some("html" | "htm") => ExportFormat::Html,
+        Some("svg") => ExportFormat::Svg,
+        _ => ExportFormat::Ansi,
+    };
+
+    println!("Connecting to iTerm2...");
+    let mut connection = ITerm2Connection::connect().await?;
+
+    let output = connection.export_session("active", range, format).await?;
+    std::fs::write(&path, output)?;
+    println!("Wrote {path}");
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use iterm2_api::ITerm2Connection;
+use iterm2_api::geometry::{Tiling, bounding_frame};
+
+/// Usage: cargo run --example tile_windows -- [grid|columns|rows|cascade]
+///
+/// Windows are arranged within the area they currently cover.
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let tiling = match std::env::args().nth(1).as_deref().unwrap_or("grid") {
+        "grid" => Tiling::Grid { columns: None },
+        "columns" => Tiling::Columns,
+        "rows" => Tiling::Rows,
+        "cascade" => Tiling::Cascade { offset: 24.0 },
+        other => return Err(format!("Unknown tiling: {other}").into()),
+    };
+
+    println!("Connecting to iTerm2...");
+    let mut connection = ITerm2Connection::connect().await?;
+
+    let frames = connection.window_frames().await?;
+    let Some(screen) = bounding_frame(frames.iter().map(|(_, frame)| frame)) else {
+        println!("No windows to arrange");
+        return Ok(());
+    };
+
+    for (window_id, frame) in connection.tile_windows(&screen, tiling).await? {
+        println!(
+            "  {window_id}: {}x{} at ({}, {})",
+            frame.size.width, frame.size.height, frame.origin.x, frame.origin.y
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use iterm2_api::ITerm2Connection;
+use iterm2_api::workspace::Workspace;
+
+/// Usage: cargo run --example workspace -- <workspace.toml|yaml> [--dry-run]
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let path = args.next().ok_or("Usage: workspace <file> [--dry-run]")?;
+    let dry_run = args.any(|arg| arg == "--dry-run");
+
+    let workspace = Workspace::load(&path)?;
+    let steps = workspace.plan()?;
+
+    if dry_run {
+        println!("Planned requests for {path}:");
+        for (i, step) in steps.iter().enumerate() {
+            println!("{:>3}. {}", i + 1, step);
+        }
+        return Ok(());
+    }
+
+    println!("Connecting to iTerm2...");
+    let mut connection = ITerm2Connection::connect().await?;
+
+    println!("Creating workspace ({} requests)...", steps.len());
+    let applied = connection.apply_workspace(&workspace).await?;
+
+    for window in &applied.windows {
+        println!("Window {}", window.window_id);
+        for tab in &window.tabs {
+            println!("  Tab {}: {} panes", tab.tab_id, tab.session_ids.len());
+        }
+    }
+
+    Ok(())
+}
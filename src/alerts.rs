@@ -0,0 +1,508 @@
+//! Alerts when long-running commands finish in the background.
+//!
+//! `CompletionAlerts` watches command starts and ends in every session with shell integration.
+//! When a command that ran longer than a threshold finishes in a session that isn't focused, it
+//! runs the configured actions. This is the Rust counterpart of the Python `autoalert` example.
+
+use crate::connection::ITerm2Connection;
+use crate::error::Result;
+use crate::generated::api::focus_changed_notification::Event;
+use crate::generated::api::{Notification, NotificationType, PromptMonitorMode};
+use crate::mru::MruTracker;
+use crate::prompt::{PromptEventKind, PromptMonitor};
+use crate::style::Rgb;
+use crate::tree;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Profile keys changed by `AlertAction::Badge` and `AlertAction::TabColor`.
+const BADGE_KEYS: [&str; 1] = ["Badge Text"];
+const TAB_COLOR_KEYS: [&str; 2] = ["Tab Color", "Use Tab Color"];
+
+/// A command that finished in the background.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletedCommand {
+    pub session_id: String,
+    pub prompt_id: Option<String>,
+    pub command: String,
+    pub exit_status: i32,
+    pub duration: Duration,
+}
+
+impl CompletedCommand {
+    /// A one-line description, e.g. `make finished with status 0 after 95s`.
+    pub fn summary(&self) -> String {
+        format!(
+            "{} finished with status {} after {}s",
+            self.command,
+            self.exit_status,
+            self.duration.as_secs()
+        )
+    }
+}
+
+/// What to do when a command finishes in the background.
+#[derive(Clone)]
+pub enum AlertAction {
+    /// Post a notification from the session, like `printf '\e]9;...\a'` would
+    Notify,
+    /// Set the session's badge
+    Badge(String),
+    /// Color the session's tab
+    TabColor(Rgb),
+    /// Call a function
+    Callback(Arc<dyn Fn(&CompletedCommand) + Send + Sync>),
+}
+
+impl fmt::Debug for AlertAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AlertAction::Notify => f.write_str("Notify"),
+            AlertAction::Badge(text) => f.debug_tuple("Badge").field(text).finish(),
+            AlertAction::TabColor(color) => f.debug_tuple("TabColor").field(color).finish(),
+            AlertAction::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
+
+/// Runs alert actions for long commands that finish in sessions the user isn't looking at.
+///
+/// Badges and tab colors set by an alert are put back when the session is next focused.
+/// An action that fails, for example because the session has just closed, is logged and skipped.
+#[derive(Debug)]
+pub struct CompletionAlerts {
+    monitor: PromptMonitor,
+    threshold: Duration,
+    actions: Vec<AlertAction>,
+    focus: MruTracker,
+    app_active: bool,
+    /// Command and start time of the command running in each session
+    running: HashMap<String, (String, Instant)>,
+    /// Profile values to restore in sessions whose badge or tab color was changed
+    restore: HashMap<String, Map<String, Value>>,
+}
+
+impl CompletionAlerts {
+    /// Start watching all sessions.
+    ///
+    /// This subscribes the connection to prompt, focus and layout notifications. Call
+    /// `next_alert()` in a loop; other notifications that arrive stay queued for other readers.
+    ///
+    /// # Arguments
+    /// * `threshold` - Commands that run at least this long trigger an alert
+    /// * `actions` - What to do, in order, for each alert
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if subscribing or querying the current state fails
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::alerts::{AlertAction, CompletionAlerts};
+    /// # use iterm2_api::style::Rgb;
+    /// # use std::sync::Arc;
+    /// # use std::time::Duration;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let actions = vec![
+    ///     AlertAction::Notify,
+    ///     AlertAction::TabColor(Rgb::new(0xd0, 0x40, 0x40)),
+    ///     AlertAction::Callback(Arc::new(|done| eprintln!("{}", done.summary()))),
+    /// ];
+    /// let mut alerts =
+    ///     CompletionAlerts::start(&mut connection, Duration::from_secs(30), actions).await?;
+    /// loop {
+    ///     alerts.next_alert(&mut connection).await?;
+    /// }
+    /// # }
+    /// ```
+    pub async fn start(
+        connection: &mut ITerm2Connection,
+        threshold: Duration,
+        actions: Vec<AlertAction>,
+    ) -> Result<Self> {
+        let modes = [
+            PromptMonitorMode::COMMAND_START,
+            PromptMonitorMode::COMMAND_END,
+        ];
+        let monitor = PromptMonitor::start(connection, "all", &modes).await?;
+        connection
+            .subscribe(NotificationType::NOTIFY_ON_FOCUS_CHANGE, None)
+            .await?;
+        connection
+            .subscribe(NotificationType::NOTIFY_ON_LAYOUT_CHANGE, None)
+            .await?;
+
+        let layout = connection.get_layout().await?;
+        let mut alerts = Self {
+            monitor,
+            threshold,
+            actions,
+            focus: MruTracker::from_layout(&layout),
+            app_active: true,
+            running: HashMap::new(),
+            restore: HashMap::new(),
+        };
+        for notification in connection.get_focus().await? {
+            alerts.focus.handle_focus(&notification);
+            if let Some(Event::ApplicationActive(active)) = notification.event {
+                alerts.app_active = active;
+            }
+        }
+        Ok(alerts)
+    }
+
+    pub fn threshold(&self) -> Duration {
+        self.threshold
+    }
+
+    /// The session the user is looking at: the active session of the selected tab of the key
+    /// window, or `None` if iTerm2 isn't the active app.
+    pub fn focused_session(&self) -> Option<&str> {
+        if !self.app_active {
+            return None;
+        }
+        let window_id = self.focus.current_window()?;
+        let tab_id = self.focus.tabs(window_id).first()?;
+        self.focus.sessions(tab_id).first().map(String::as_str)
+    }
+
+    /// Track a prompt, focus or layout notification.
+    ///
+    /// Sessions that disappear from the layout are forgotten, along with any badge or tab color
+    /// waiting to be restored in them.
+    ///
+    /// # Returns
+    /// The finished command, if it should trigger an alert
+    pub fn handle_notification(&mut self, notification: &Notification) -> Option<CompletedCommand> {
+        self.focus.handle_notification(notification);
+        if let Some(focus) = notification.focus_changed_notification.as_ref()
+            && let Some(Event::ApplicationActive(active)) = focus.event
+        {
+            self.app_active = active;
+        }
+        if let Some(layout) = notification.layout_changed_notification.as_ref() {
+            let layout = &layout.list_sessions_response;
+            let open = |session_id: &String| tree::find_session(layout, session_id).is_some();
+            self.running.retain(|session_id, _| open(session_id));
+            self.restore.retain(|session_id, _| open(session_id));
+        }
+
+        let event = self.monitor.handle_notification(notification)?;
+        match event.kind {
+            PromptEventKind::CommandStart { command } => {
+                self.running
+                    .insert(event.session_id, (command, event.received));
+                None
+            }
+            PromptEventKind::CommandEnd { exit_status } => {
+                let (command, started) = self.running.remove(&event.session_id)?;
+                let duration = event.received.duration_since(started);
+                if duration < self.threshold
+                    || self.focused_session() == Some(event.session_id.as_str())
+                {
+                    return None;
+                }
+                Some(CompletedCommand {
+                    session_id: event.session_id,
+                    prompt_id: event.prompt_id,
+                    command,
+                    exit_status,
+                    duration,
+                })
+            }
+            PromptEventKind::Prompt { .. } => None,
+        }
+    }
+
+    /// Whether a notification is one `handle_notification()` tracks.
+    pub fn wants(&self, notification: &Notification) -> bool {
+        self.monitor.wants(notification)
+            || notification.focus_changed_notification.is_some()
+            || notification.layout_changed_notification.is_some()
+    }
+
+    /// Take the badge and tab color to put back in the focused session, if an alert changed
+    /// them.
+    fn take_restore(&mut self) -> Option<(String, Map<String, Value>)> {
+        let session_id = self.focused_session()?.to_string();
+        let properties = self.restore.remove(&session_id)?;
+        Some((session_id, properties))
+    }
+
+    /// Wait for the next alert and run its actions.
+    ///
+    /// Between alerts this also restores the badge and tab color of sessions as they are
+    /// focused. Failures to restore them are logged.
+    ///
+    /// # Errors
+    /// Returns `Error::Connection` if the connection is closed
+    pub async fn next_alert(
+        &mut self,
+        connection: &mut ITerm2Connection,
+    ) -> Result<CompletedCommand> {
+        loop {
+            let notification = connection
+                .next_notification_where(|n| self.wants(n))
+                .await?;
+            let completed = self.handle_notification(&notification);
+            if notification.focus_changed_notification.is_some()
+                && let Some((session_id, properties)) = self.take_restore()
+                && let Err(e) = connection
+                    .set_profile_properties(&session_id, &properties)
+                    .await
+            {
+                tracing::warn!("Failed to restore the profile of session {session_id}: {e}");
+            }
+            if let Some(completed) = completed {
+                self.alert(connection, &completed).await;
+                return Ok(completed);
+            }
+        }
+    }
+
+    /// Run the configured actions for a finished command.
+    ///
+    /// Every action is tried in order. One that fails, for example because the session has
+    /// closed, is logged and doesn't stop the others.
+    pub async fn alert(&mut self, connection: &mut ITerm2Connection, completed: &CompletedCommand) {
+        for action in &self.actions {
+            if let Err(e) = run_action(&mut self.restore, connection, action, completed).await {
+                tracing::warn!(
+                    "Alert action {action:?} failed in session {}: {e}",
+                    completed.session_id
+                );
+            }
+        }
+    }
+
+    /// Unsubscribe from the notifications used.
+    ///
+    /// Badges and tab colors that are still set are left as they are.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if unsubscribing fails
+    pub async fn stop(self, connection: &mut ITerm2Connection) -> Result<()> {
+        self.monitor.stop(connection).await?;
+        connection
+            .unsubscribe(NotificationType::NOTIFY_ON_FOCUS_CHANGE, None)
+            .await?;
+        connection
+            .unsubscribe(NotificationType::NOTIFY_ON_LAYOUT_CHANGE, None)
+            .await
+    }
+}
+
+/// Run one alert action.
+async fn run_action(
+    restore: &mut HashMap<String, Map<String, Value>>,
+    connection: &mut ITerm2Connection,
+    action: &AlertAction,
+    completed: &CompletedCommand,
+) -> Result<()> {
+    let session_id = completed.session_id.as_str();
+    match action {
+        AlertAction::Notify => {
+            // OSC 9 text ends at the first control character
+            let text: String = completed
+                .summary()
+                .chars()
+                .filter(|c| !c.is_control())
+                .collect();
+            connection
+                .inject(session_id, format!("\x1b]9;{text}\x07").as_bytes())
+                .await?;
+        }
+        AlertAction::Badge(text) => {
+            save_properties(restore, connection, session_id, &BADGE_KEYS).await?;
+            let mut properties = Map::new();
+            properties.insert("Badge Text".to_string(), Value::from(text.as_str()));
+            connection
+                .set_profile_properties(session_id, &properties)
+                .await?;
+        }
+        AlertAction::TabColor(color) => {
+            save_properties(restore, connection, session_id, &TAB_COLOR_KEYS).await?;
+            let mut properties = Map::new();
+            properties.insert("Tab Color".to_string(), color.to_profile_color());
+            properties.insert("Use Tab Color".to_string(), Value::Bool(true));
+            connection
+                .set_profile_properties(session_id, &properties)
+                .await?;
+        }
+        AlertAction::Callback(callback) => callback(completed),
+    }
+    Ok(())
+}
+
+/// Remember a session's current values for `keys`, unless an earlier alert already did.
+async fn save_properties(
+    restore: &mut HashMap<String, Map<String, Value>>,
+    connection: &mut ITerm2Connection,
+    session_id: &str,
+    keys: &[&str],
+) -> Result<()> {
+    let saved = restore.entry(session_id.to_string()).or_default();
+    if keys.iter().all(|key| saved.contains_key(*key)) {
+        return Ok(());
+    }
+    let current = connection.get_profile_properties(session_id, keys).await?;
+    for key in keys {
+        let value = current.get(*key).cloned().unwrap_or(match *key {
+            "Use Tab Color" => Value::Bool(false),
+            _ => Value::from(""),
+        });
+        saved.entry(key.to_string()).or_insert(value);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generated::api::list_sessions_response::{Tab, Window};
+    use crate::generated::api::split_tree_node::SplitTreeLink;
+    use crate::generated::api::{FocusChangedNotification, ListSessionsResponse, SessionSummary};
+
+    /// One window with a tab per session: `t1` holds `s1`, `t2` holds `s2`, and so on.
+    fn layout(sessions: &[&str]) -> ListSessionsResponse {
+        let mut window = Window::new();
+        window.set_window_id("w1".to_string());
+        for session_id in sessions {
+            let mut session = SessionSummary::new();
+            session.set_unique_identifier(session_id.to_string());
+            let mut link = SplitTreeLink::new();
+            link.set_session(session);
+            let mut tab = Tab::new();
+            tab.set_tab_id(session_id.replace('s', "t"));
+            tab.root.mut_or_insert_default().links.push(link);
+            window.tabs.push(tab);
+        }
+        let mut layout = ListSessionsResponse::new();
+        layout.windows.push(window);
+        layout
+    }
+
+    fn alerts() -> CompletionAlerts {
+        CompletionAlerts {
+            monitor: PromptMonitor::subscribed("all"),
+            threshold: Duration::from_secs(30),
+            actions: Vec::new(),
+            focus: MruTracker::from_layout(&layout(&["s1", "s2"])),
+            app_active: true,
+            running: HashMap::new(),
+            restore: HashMap::new(),
+        }
+    }
+
+    fn command_start(session_id: &str, command: &str) -> Notification {
+        let mut notification = Notification::new();
+        let prompt = notification.prompt_notification.mut_or_insert_default();
+        prompt.set_session(session_id.to_string());
+        prompt.mut_command_start().set_command(command.to_string());
+        notification
+    }
+
+    fn command_end(session_id: &str, status: i32) -> Notification {
+        let mut notification = Notification::new();
+        let prompt = notification.prompt_notification.mut_or_insert_default();
+        prompt.set_session(session_id.to_string());
+        prompt.mut_command_end().set_status(status);
+        notification
+    }
+
+    fn focus(edit: impl FnOnce(&mut FocusChangedNotification)) -> Notification {
+        let mut notification = Notification::new();
+        edit(
+            notification
+                .focus_changed_notification
+                .mut_or_insert_default(),
+        );
+        notification
+    }
+
+    fn layout_changed(sessions: &[&str]) -> Notification {
+        let mut notification = Notification::new();
+        notification
+            .layout_changed_notification
+            .mut_or_insert_default()
+            .list_sessions_response = protobuf::MessageField::some(layout(sessions));
+        notification
+    }
+
+    /// Start `command` in a session as if it began `ago`.
+    fn started(alerts: &mut CompletionAlerts, session_id: &str, command: &str, ago: Duration) {
+        assert_eq!(
+            alerts.handle_notification(&command_start(session_id, command)),
+            None
+        );
+        alerts.running.get_mut(session_id).unwrap().1 -= ago;
+    }
+
+    #[test]
+    fn only_long_commands_alert() {
+        let mut alerts = alerts();
+        started(&mut alerts, "s2", "make", Duration::from_secs(95));
+        let completed = alerts.handle_notification(&command_end("s2", 2)).unwrap();
+        assert_eq!(completed.command, "make");
+        assert_eq!(completed.exit_status, 2);
+        assert!(completed.duration >= Duration::from_secs(95));
+        assert!(
+            completed
+                .summary()
+                .starts_with("make finished with status 2 after 9")
+        );
+
+        started(&mut alerts, "s2", "ls", Duration::from_secs(1));
+        assert_eq!(alerts.handle_notification(&command_end("s2", 0)), None);
+        // An end without a start is ignored
+        assert_eq!(alerts.handle_notification(&command_end("s2", 0)), None);
+    }
+
+    #[test]
+    fn focused_sessions_do_not_alert() {
+        let mut alerts = alerts();
+        assert_eq!(alerts.focused_session(), Some("s1"));
+        started(&mut alerts, "s1", "make", Duration::from_secs(60));
+        assert_eq!(alerts.handle_notification(&command_end("s1", 0)), None);
+
+        // Once the user switches to another app, nothing is focused
+        alerts.handle_notification(&focus(|f| f.set_application_active(false)));
+        assert_eq!(alerts.focused_session(), None);
+        started(&mut alerts, "s1", "make", Duration::from_secs(60));
+        assert!(alerts.handle_notification(&command_end("s1", 0)).is_some());
+
+        alerts.handle_notification(&focus(|f| f.set_application_active(true)));
+        alerts.handle_notification(&focus(|f| f.set_selected_tab("t2".to_string())));
+        assert_eq!(alerts.focused_session(), Some("s2"));
+    }
+
+    #[test]
+    fn profiles_are_restored_when_focused() {
+        let mut alerts = alerts();
+        let mut saved = Map::new();
+        saved.insert("Badge Text".to_string(), Value::from(""));
+        alerts.restore.insert("s2".to_string(), saved.clone());
+
+        assert_eq!(alerts.take_restore(), None);
+        alerts.handle_notification(&focus(|f| f.set_selected_tab("t2".to_string())));
+        assert_eq!(alerts.take_restore(), Some(("s2".to_string(), saved)));
+        assert_eq!(alerts.take_restore(), None);
+    }
+
+    #[test]
+    fn closed_sessions_are_forgotten() {
+        let mut alerts = alerts();
+        started(&mut alerts, "s2", "make", Duration::from_secs(60));
+        alerts.restore.insert("s2".to_string(), Map::new());
+        alerts.restore.insert("s1".to_string(), Map::new());
+
+        alerts.handle_notification(&layout_changed(&["s1"]));
+        assert!(alerts.running.is_empty());
+        assert_eq!(alerts.restore.keys().collect::<Vec<_>>(), ["s1"]);
+    }
+}
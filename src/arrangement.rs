@@ -0,0 +1,115 @@
+//! Saved window arrangements.
+//!
+//! An arrangement records the windows, tabs and split panes of a moment so they can be reopened
+//! later, either as new windows or as tabs in an existing window.
+
+use crate::connection::ITerm2Connection;
+use crate::error::{Error, Result};
+use crate::generated::api::saved_arrangement_request::Action;
+use crate::generated::api::*;
+
+impl ITerm2Connection {
+    /// List the names of all saved window arrangements.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if the request fails
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// for name in connection.list_arrangements().await? {
+    ///     println!("  Arrangement: {name}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn list_arrangements(&mut self) -> Result<Vec<String>> {
+        self.saved_arrangement(Action::LIST, None, None).await
+    }
+
+    /// Save windows as a named arrangement.
+    ///
+    /// # Arguments
+    /// * `name` - The arrangement name. An existing arrangement with this name is replaced.
+    /// * `window_id` - Save only the tabs of this window, or `None` to save all windows
+    ///
+    /// # Errors
+    /// Returns `Error::WindowNotFound` if the window doesn't exist
+    pub async fn save_arrangement(&mut self, name: &str, window_id: Option<&str>) -> Result<()> {
+        self.saved_arrangement(Action::SAVE, Some(name), window_id)
+            .await?;
+        Ok(())
+    }
+
+    /// Restore a named arrangement.
+    ///
+    /// # Arguments
+    /// * `name` - The arrangement to restore
+    /// * `window_id` - Restore as tabs in this window, or `None` to open new windows
+    ///
+    /// # Errors
+    /// Returns `Error::ArrangementNotFound` if there is no arrangement with that name, or
+    /// `Error::WindowNotFound` if the window doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// connection.save_arrangement("morning", None).await?;
+    ///
+    /// // Later: bring everything back in new windows
+    /// connection.restore_arrangement("morning", None).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn restore_arrangement(&mut self, name: &str, window_id: Option<&str>) -> Result<()> {
+        self.saved_arrangement(Action::RESTORE, Some(name), window_id)
+            .await?;
+        Ok(())
+    }
+
+    async fn saved_arrangement(
+        &mut self,
+        action: Action,
+        name: Option<&str>,
+        window_id: Option<&str>,
+    ) -> Result<Vec<String>> {
+        let mut request = SavedArrangementRequest::new();
+        request.set_action(action);
+        if let Some(name) = name {
+            request.set_name(name.to_string());
+        }
+        if let Some(window_id) = window_id {
+            request.set_window_id(window_id.to_string());
+        }
+
+        let mut message = ClientOriginatedMessage::new();
+        message.set_saved_arrangement_request(request);
+
+        let mut response = self.call(message).await?;
+
+        if !response.has_saved_arrangement_response() {
+            return Err(Error::Api(
+                "Expected saved arrangement response".to_string(),
+            ));
+        }
+        let arrangement_response = response.take_saved_arrangement_response();
+        match arrangement_response.status() {
+            saved_arrangement_response::Status::OK => Ok(arrangement_response.names),
+            saved_arrangement_response::Status::ARRANGEMENT_NOT_FOUND => Err(
+                Error::ArrangementNotFound(name.unwrap_or_default().to_string()),
+            ),
+            saved_arrangement_response::Status::WINDOW_NOT_FOUND => Err(Error::WindowNotFound(
+                window_id.unwrap_or_default().to_string(),
+            )),
+            status => Err(Error::Api(format!(
+                "Saved arrangement {action:?} failed: {status:?}"
+            ))),
+        }
+    }
+}
@@ -0,0 +1,349 @@
+//! Reading screen and scrollback contents.
+//!
+//! Lines are numbered the way iTerm2 numbers them: line 0 is the first line the session ever
+//! had, and numbers stay stable when old lines are dropped from the head of history. A long line
+//! that wrapped on screen appears as several `BufferLine`s, all but the last soft-wrapped; use
+//! `Buffer::logical_lines()` or `join_wrapped()` to put them back together.
+
+use crate::connection::ITerm2Connection;
+use crate::error::{Error, Result};
+use crate::generated::api::line_contents::Continuation;
+use crate::generated::api::*;
+
+/// Which lines to read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BufferRange {
+    /// The lines currently on screen
+    Screen,
+    /// The last `n` lines, reaching back into scrollback history if needed
+    Trailing(i32),
+    /// An explicit range of lines, optionally limited to some columns
+    Windowed(WindowedCoordRange),
+}
+
+impl BufferRange {
+    /// Lines `first..end` by stable line number, all columns.
+    pub fn lines(first: i64, end: i64) -> Self {
+        let mut range = WindowedCoordRange::new();
+        let coord_range = range.coord_range.mut_or_insert_default();
+        coord_range.start.mut_or_insert_default().set_y(first);
+        coord_range.start.mut_or_insert_default().set_x(0);
+        coord_range.end.mut_or_insert_default().set_y(end);
+        coord_range.end.mut_or_insert_default().set_x(0);
+        BufferRange::Windowed(range)
+    }
+
+    fn to_line_range(&self) -> LineRange {
+        let mut line_range = LineRange::new();
+        match self {
+            BufferRange::Screen => line_range.set_screen_contents_only(true),
+            BufferRange::Trailing(n) => line_range.set_trailing_lines(*n),
+            BufferRange::Windowed(range) => {
+                line_range.windowed_coord_range = protobuf::MessageField::some(range.clone())
+            }
+        }
+        line_range
+    }
+}
+
+/// A cell position, with the column clamped to what a `Coord` can hold.
+pub(crate) fn coord(x: usize, y: i64) -> Coord {
+    let mut coord = Coord::new();
+    coord.set_x(i32::try_from(x).unwrap_or(i32::MAX));
+    coord.set_y(y);
+    coord
+}
+
+/// One screen line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferLine {
+    /// Stable line number
+    pub y: i64,
+    pub text: String,
+    /// Whether the next line continues this one because it wrapped
+    pub soft_wrapped: bool,
+    /// Code points per cell, run-length encoded. See `LineContents` in the API docs.
+    pub code_points_per_cell: Vec<CodePointsPerCell>,
+    /// Cell styles, run-length encoded. Empty unless styles were requested.
+    pub styles: Vec<CellStyle>,
+}
+
+impl BufferLine {
+    fn from_contents(y: i64, mut contents: LineContents) -> Self {
+        Self {
+            y,
+            soft_wrapped: contents.continuation() == Continuation::CONTINUATION_SOFT_EOL,
+            text: contents.take_text(),
+            code_points_per_cell: contents.code_points_per_cell,
+            styles: contents.style,
+        }
+    }
+}
+
+/// A line of output as the program wrote it, joined from one or more wrapped screen lines.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogicalLine {
+    /// Stable line number of the first screen line
+    pub y: i64,
+    /// Number of screen lines it spans
+    pub rows: usize,
+    pub text: String,
+}
+
+/// Lines read from a session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Buffer {
+    pub lines: Vec<BufferLine>,
+    /// Cursor position, using stable line numbers
+    pub cursor: Coord,
+    /// The range that was actually returned
+    pub range: WindowedCoordRange,
+}
+
+impl Buffer {
+    /// Join soft-wrapped lines into logical lines.
+    pub fn logical_lines(&self) -> Vec<LogicalLine> {
+        join_wrapped(&self.lines)
+    }
+
+    /// All lines as text, one per screen line.
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Join soft-wrapped screen lines into logical lines.
+///
+/// A trailing line that is soft-wrapped (because the range ended mid-line) is still returned.
+///
+/// # Example
+/// ```rust
+/// use iterm2_api::buffer::{BufferLine, join_wrapped};
+///
+/// let line = |y, text: &str, soft_wrapped| BufferLine {
+///     y,
+///     text: text.to_string(),
+///     soft_wrapped,
+///     code_points_per_cell: Vec::new(),
+///     styles: Vec::new(),
+/// };
+/// let lines = [line(10, "$ echo a very long", true), line(11, " line", false), line(12, "$", false)];
+///
+/// let logical = join_wrapped(&lines);
+/// assert_eq!(logical.len(), 2);
+/// assert_eq!(logical[0].text, "$ echo a very long line");
+/// assert_eq!((logical[0].y, logical[0].rows), (10, 2));
+/// assert_eq!(logical[1].y, 12);
+/// ```
+pub fn join_wrapped(lines: &[BufferLine]) -> Vec<LogicalLine> {
+    let mut logical: Vec<LogicalLine> = Vec::new();
+    let mut continuing = false;
+    for line in lines {
+        match logical.last_mut() {
+            Some(last) if continuing => {
+                last.text.push_str(&line.text);
+                last.rows += 1;
+            }
+            _ => logical.push(LogicalLine {
+                y: line.y,
+                rows: 1,
+                text: line.text.clone(),
+            }),
+        }
+        continuing = line.soft_wrapped;
+    }
+    logical
+}
+
+impl ITerm2Connection {
+    /// Read lines from a session's screen or scrollback history.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session to read. `"active"` is accepted; `"all"` is not.
+    /// * `range` - Which lines to read
+    /// * `include_styles` - Also return cell styles (colors, bold, links, ...)
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist, or `Error::Api` if the
+    /// range is invalid
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::buffer::BufferRange;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let buffer = connection
+    ///     .get_buffer("active", BufferRange::Trailing(100), false)
+    ///     .await?;
+    /// for line in buffer.logical_lines() {
+    ///     println!("{:>6} {}", line.y, line.text);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_buffer(
+        &mut self,
+        session_id: &str,
+        range: BufferRange,
+        include_styles: bool,
+    ) -> Result<Buffer> {
+        let response = self
+            .call(buffer_request(session_id, &range, include_styles))
+            .await?;
+        buffer_from_response(response, session_id)
+    }
+}
+
+pub(crate) fn buffer_request(
+    session_id: &str,
+    range: &BufferRange,
+    include_styles: bool,
+) -> ClientOriginatedMessage {
+    let mut request = GetBufferRequest::new();
+    request.set_session(session_id.to_string());
+    request.line_range = protobuf::MessageField::some(range.to_line_range());
+    request.set_include_styles(include_styles);
+
+    let mut message = ClientOriginatedMessage::new();
+    message.set_get_buffer_request(request);
+    message
+}
+
+pub(crate) fn buffer_from_response(
+    mut response: ServerOriginatedMessage,
+    session_id: &str,
+) -> Result<Buffer> {
+    if !response.has_get_buffer_response() {
+        return Err(Error::Api("Expected get buffer response".to_string()));
+    }
+    let mut buffer_response = response.take_get_buffer_response();
+    match buffer_response.status() {
+        get_buffer_response::Status::OK => {}
+        get_buffer_response::Status::SESSION_NOT_FOUND => {
+            return Err(Error::SessionNotFound(session_id.to_string()));
+        }
+        status => return Err(Error::Api(format!("Get buffer failed: {status:?}"))),
+    }
+
+    let range = buffer_response
+        .windowed_coord_range
+        .take()
+        .unwrap_or_default();
+    let first = range.coord_range.start.y();
+    let lines = std::mem::take(&mut buffer_response.contents)
+        .into_iter()
+        .zip(first..)
+        .map(|(contents, y)| BufferLine::from_contents(y, contents))
+        .collect();
+
+    Ok(Buffer {
+        lines,
+        cursor: buffer_response.cursor.take().unwrap_or_default(),
+        range,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents(text: &str, continuation: Continuation) -> LineContents {
+        let mut contents = LineContents::new();
+        contents.set_text(text.to_string());
+        contents.set_continuation(continuation);
+        contents
+    }
+
+    fn response(first: i64, lines: Vec<LineContents>) -> ServerOriginatedMessage {
+        let mut buffer = GetBufferResponse::new();
+        buffer.set_status(get_buffer_response::Status::OK);
+        buffer
+            .windowed_coord_range
+            .mut_or_insert_default()
+            .coord_range
+            .mut_or_insert_default()
+            .start
+            .mut_or_insert_default()
+            .set_y(first);
+        buffer.contents = lines;
+        let mut message = ServerOriginatedMessage::new();
+        message.set_get_buffer_response(buffer);
+        message
+    }
+
+    #[test]
+    fn lines_are_numbered_from_the_range_start() {
+        let message = response(
+            40,
+            vec![
+                contents("first half ", Continuation::CONTINUATION_SOFT_EOL),
+                contents("second half", Continuation::CONTINUATION_HARD_EOL),
+                contents("next", Continuation::CONTINUATION_HARD_EOL),
+            ],
+        );
+        let buffer = buffer_from_response(message, "s1").unwrap();
+        let numbered: Vec<(i64, bool)> = buffer
+            .lines
+            .iter()
+            .map(|line| (line.y, line.soft_wrapped))
+            .collect();
+        assert_eq!(numbered, vec![(40, true), (41, false), (42, false)]);
+        assert_eq!(buffer.text(), "first half \nsecond half\nnext");
+
+        let logical = buffer.logical_lines();
+        assert_eq!(
+            logical,
+            vec![
+                LogicalLine {
+                    y: 40,
+                    rows: 2,
+                    text: "first half second half".to_string()
+                },
+                LogicalLine {
+                    y: 42,
+                    rows: 1,
+                    text: "next".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn join_wrapped_keeps_a_trailing_partial_line() {
+        let message = response(
+            0,
+            vec![
+                contents("a", Continuation::CONTINUATION_SOFT_EOL),
+                contents("b", Continuation::CONTINUATION_SOFT_EOL),
+                contents("c", Continuation::CONTINUATION_SOFT_EOL),
+            ],
+        );
+        let buffer = buffer_from_response(message, "s1").unwrap();
+        let logical = join_wrapped(&buffer.lines);
+        assert_eq!(logical.len(), 1);
+        assert_eq!((logical[0].rows, logical[0].text.as_str()), (3, "abc"));
+        assert!(join_wrapped(&[]).is_empty());
+    }
+
+    #[test]
+    fn missing_sessions_are_reported() {
+        let mut message = response(0, Vec::new());
+        message
+            .mut_get_buffer_response()
+            .set_status(get_buffer_response::Status::SESSION_NOT_FOUND);
+        assert!(matches!(
+            buffer_from_response(message, "s1"),
+            Err(Error::SessionNotFound(id)) if id == "s1"
+        ));
+        assert!(matches!(
+            buffer_from_response(ServerOriginatedMessage::new(), "s1"),
+            Err(Error::Api(_))
+        ));
+    }
+}
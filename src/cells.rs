@@ -0,0 +1,240 @@
+//! Mapping line text to screen columns.
+//!
+//! `LineContents.text` holds a line's code points without regard to cells: a cell may contain
+//! several code points (a letter plus combining marks) or none (an uninitialized cell, or the
+//! right half of a double-width character). `CellGrid` decodes `code_points_per_cell` into one
+//! string per column and keeps index maps in both directions. Text indices count code points
+//! (`char`s), as in the API documentation, not bytes.
+
+use crate::buffer::BufferLine;
+use crate::generated::api::CodePointsPerCell;
+use unicode_width::UnicodeWidthChar;
+
+/// The cells of one screen line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CellGrid {
+    cells: Vec<String>,
+    /// For each cell, the index of its first code point in the line text
+    cell_to_text: Vec<usize>,
+    /// For each code point in the line text, the column of the cell containing it
+    text_to_cell: Vec<usize>,
+}
+
+impl CellGrid {
+    /// Decode a line from its text and run-length encoded code point counts.
+    ///
+    /// Code points beyond those accounted for by `code_points_per_cell` are ignored.
+    ///
+    /// # Example
+    /// ```rust
+    /// use iterm2_api::cells::CellGrid;
+    /// use iterm2_api::generated::api::CodePointsPerCell;
+    ///
+    /// let run = |num_code_points, repeats| {
+    ///     let mut cpc = CodePointsPerCell::new();
+    ///     cpc.set_num_code_points(num_code_points);
+    ///     cpc.set_repeats(repeats);
+    ///     cpc
+    /// };
+    /// // "xyz compañía" with an uninitialized cell after "xyz" and decomposed ñ and í
+    /// let text = "xyzcompan\u{303}i\u{301}a";
+    /// let grid = CellGrid::decode(text, &[run(1, 3), run(0, 1), run(1, 5), run(2, 2), run(1, 1)]);
+    ///
+    /// assert_eq!(grid.width(), 12);
+    /// assert_eq!(grid.cell(3), Some(""));
+    /// assert_eq!(grid.cell(9), Some("n\u{303}"));
+    /// assert_eq!(grid.column_of(11), Some(10)); // the combining acute belongs to í
+    /// assert_eq!(grid.text_index(11), Some(12));
+    /// assert_eq!(grid.text_in(4..9), "compa");
+    /// ```
+    pub fn decode(text: &str, code_points_per_cell: &[CodePointsPerCell]) -> Self {
+        let mut chars = text.chars();
+        let mut grid = Self::default();
+        let mut text_index = 0;
+        for run in code_points_per_cell {
+            let count = usize::try_from(run.num_code_points()).unwrap_or(0);
+            for _ in 0..run.repeats() {
+                let column = grid.cells.len();
+                let cell: String = chars.by_ref().take(count).collect();
+                let taken = cell.chars().count();
+                grid.cell_to_text.push(text_index);
+                grid.text_to_cell.extend(std::iter::repeat_n(column, taken));
+                grid.cells.push(cell);
+                text_index += taken;
+            }
+        }
+        grid
+    }
+
+    /// Decode a line read with `get_buffer()`.
+    pub fn from_line(line: &BufferLine) -> Self {
+        Self::decode(&line.text, &line.code_points_per_cell)
+    }
+
+    /// The number of decoded cells. Trailing uninitialized cells are not included, so this may
+    /// be less than the session width.
+    pub fn width(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// The cells' contents, one string per column. Empty strings are uninitialized cells.
+    pub fn cells(&self) -> &[String] {
+        &self.cells
+    }
+
+    /// The contents of the cell at `column`, or `None` past the end of the line.
+    pub fn cell(&self, column: usize) -> Option<&str> {
+        self.cells.get(column).map(String::as_str)
+    }
+
+    /// The column of the cell containing code point `text_index`.
+    pub fn column_of(&self, text_index: usize) -> Option<usize> {
+        self.text_to_cell.get(text_index).copied()
+    }
+
+    /// The index of the first code point at or after `column`.
+    ///
+    /// For an uninitialized cell this is where its text would be. `column == width()` maps to
+    /// the end of the text, so ranges of columns map to ranges of text.
+    pub fn text_index(&self, column: usize) -> Option<usize> {
+        match self.cell_to_text.get(column) {
+            Some(index) => Some(*index),
+            None if column == self.cells.len() => Some(self.text_to_cell.len()),
+            None => None,
+        }
+    }
+
+    /// The text of a range of columns, with uninitialized cells as spaces.
+    ///
+    /// Columns past the end of the line are treated as uninitialized. The right half of a
+    /// double-width character has no code points of its own and is skipped when the cell before
+    /// it is in the range, so that a column range reads the way it looks on screen.
+    pub fn text_in(&self, columns: std::ops::Range<usize>) -> String {
+        let start = columns.start;
+        columns
+            .filter_map(|column| match self.cell(column) {
+                Some("") if column > start && self.is_wide(column - 1) => None,
+                Some(cell) if !cell.is_empty() => Some(cell),
+                _ => Some(" "),
+            })
+            .collect()
+    }
+
+    fn is_wide(&self, column: usize) -> bool {
+        self.cell(column)
+            .and_then(|cell| cell.chars().next())
+            .is_some_and(is_wide_char)
+    }
+
+    /// Whether `column` is the empty right half of a double-width character.
+    pub fn is_wide_continuation(&self, column: usize) -> bool {
+        column > 0 && self.cell(column) == Some("") && self.is_wide(column - 1)
+    }
+}
+
+impl BufferLine {
+    /// Decode this line into cells.
+    pub fn cells(&self) -> CellGrid {
+        CellGrid::from_line(self)
+    }
+}
+
+/// Whether a character takes two cells: East Asian wide and fullwidth characters, and emoji
+/// shown in emoji presentation.
+fn is_wide_char(c: char) -> bool {
+    c.width() == Some(2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(num_code_points: i32, repeats: i32) -> CodePointsPerCell {
+        let mut run = CodePointsPerCell::new();
+        run.set_num_code_points(num_code_points);
+        run.set_repeats(repeats);
+        run
+    }
+
+    #[test]
+    fn decode_maps_both_ways() {
+        // "a", an uninitialized cell, "e" + combining acute, "b"
+        let grid = CellGrid::decode("ae\u{301}b", &[run(1, 1), run(0, 1), run(2, 1), run(1, 1)]);
+        assert_eq!(grid.cells(), ["a", "", "e\u{301}", "b"]);
+        assert_eq!(
+            (0..5).map(|i| grid.column_of(i)).collect::<Vec<_>>(),
+            vec![Some(0), Some(2), Some(2), Some(3), None]
+        );
+        assert_eq!(
+            (0..6).map(|c| grid.text_index(c)).collect::<Vec<_>>(),
+            vec![Some(0), Some(1), Some(1), Some(3), Some(4), None]
+        );
+    }
+
+    #[test]
+    fn decode_ignores_unaccounted_text() {
+        let grid = CellGrid::decode("abcdef", &[run(1, 2)]);
+        assert_eq!(grid.width(), 2);
+        assert_eq!(grid.text_index(2), Some(2));
+        assert_eq!(grid.column_of(2), None);
+
+        // Runs longer than the text leave empty cells
+        let grid = CellGrid::decode("ab", &[run(1, 4)]);
+        assert_eq!(grid.cells(), ["a", "b", "", ""]);
+        assert_eq!(CellGrid::decode("", &[]), CellGrid::default());
+    }
+
+    #[test]
+    fn text_in_reads_like_the_screen() {
+        // "日本" takes four cells; "x" follows after a gap
+        let grid = CellGrid::decode(
+            "日本x",
+            &[run(1, 1), run(0, 1), run(1, 1), run(0, 2), run(1, 1)],
+        );
+        assert_eq!(grid.width(), 6);
+        assert_eq!(grid.text_in(0..4), "日本");
+        // Starting on the right half of a wide character shows it as blank
+        assert_eq!(grid.text_in(1..3), " 本");
+        // The gap after the wide character is an uninitialized cell
+        assert_eq!(grid.text_in(2..6), "本 x");
+        assert_eq!(grid.text_in(5..8), "x  ");
+        assert_eq!(grid.text_in(3..3), "");
+    }
+
+    #[test]
+    fn wide_characters() {
+        for c in [
+            '日',
+            '한',
+            'ア',
+            'Ａ',
+            '\u{3000}',
+            '😀',
+            '🤖',
+            '🚀',
+            '🛸',
+            '☔',
+            '⚡',
+            '✅',
+            '🫠',
+            '\u{20000}',
+        ] {
+            assert!(is_wide_char(c), "{c:?} should be wide");
+        }
+        for c in ['a', 'é', 'Ж', '→', '\u{301}', '｡', '☀', '✓'] {
+            assert!(!is_wide_char(c), "{c:?} should be narrow");
+        }
+    }
+
+    #[test]
+    fn from_line_uses_the_line_runs() {
+        let line = BufferLine {
+            y: 3,
+            text: "hi".to_string(),
+            soft_wrapped: false,
+            code_points_per_cell: vec![run(1, 2)],
+            styles: Vec::new(),
+        };
+        assert_eq!(line.cells().cells(), ["h", "i"]);
+    }
+}
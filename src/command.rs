@@ -0,0 +1,141 @@
+//! Running a command in a session and waiting for it to finish.
+//!
+//! Shell integration reports when a command starts and ends, so there's no need to guess with
+//! sleeps or by matching the prompt's text.
+
+use crate::connection::ITerm2Connection;
+use crate::error::{Error, Result};
+use crate::generated::api::PromptMonitorMode;
+use crate::prompt::{PromptEventKind, PromptMonitor};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How a command run with `ITerm2Connection::run()` ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandOutcome {
+    /// The command as the shell reported it
+    pub command: String,
+    pub prompt_id: Option<String>,
+    pub exit_status: i32,
+    /// Time from the command starting to it ending
+    pub duration: Duration,
+    pub output: String,
+    /// Whether the start of the output was dropped from scrollback history
+    pub output_truncated: bool,
+}
+
+impl CommandOutcome {
+    pub fn success(&self) -> bool {
+        self.exit_status == 0
+    }
+}
+
+impl ITerm2Connection {
+    /// Run a command at a session's shell prompt and wait for it to finish.
+    ///
+    /// The command is typed into the session followed by a carriage return. It should be
+    /// entered at an idle prompt; anything already typed there becomes part of the command.
+    /// Other notifications that arrive while waiting stay queued for other readers.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session to run in. Use a real session ID, not `"active"`.
+    /// * `command` - The command line to run
+    /// * `timeout` - How long to wait for the command to finish
+    ///
+    /// # Errors
+    /// Returns `Error::ShellIntegrationUnavailable` if shell integration isn't installed in the
+    /// session, `Error::SessionNotFound` if the session doesn't exist, or `Error::Timeout` if the
+    /// command doesn't finish in time
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use std::time::Duration;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let outcome = connection
+    ///     .run("SESSION-ID", "cargo test", Duration::from_secs(600))
+    ///     .await?;
+    /// if !outcome.success() {
+    ///     eprintln!("exit {} after {:?}:\n{}", outcome.exit_status, outcome.duration, outcome.output);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn run(
+        &mut self,
+        session_id: &str,
+        command: &str,
+        timeout: Duration,
+    ) -> Result<CommandOutcome> {
+        // Fails early if there is no shell integration to report the command's end
+        self.get_prompt(session_id).await?;
+
+        let modes = [
+            PromptMonitorMode::COMMAND_START,
+            PromptMonitorMode::COMMAND_END,
+        ];
+        let monitor = PromptMonitor::start(self, session_id, &modes).await?;
+        let outcome = self.run_monitored(&monitor, command, timeout).await;
+        let stopped = monitor.stop(self).await;
+        let outcome = outcome?;
+        stopped?;
+        Ok(outcome)
+    }
+
+    async fn run_monitored(
+        &mut self,
+        monitor: &PromptMonitor,
+        command: &str,
+        timeout: Duration,
+    ) -> Result<CommandOutcome> {
+        let session_id = monitor.session_id();
+        let deadline = Instant::now() + timeout;
+        self.send_text(session_id, &format!("{}\r", command.trim_end()))
+            .await?;
+
+        let mut started = None;
+        let (prompt_id, exit_status, duration) = loop {
+            let event = tokio::time::timeout_at(deadline, monitor.next_event(self))
+                .await
+                .map_err(|_| {
+                    Error::Timeout(format!("`{command}` in session {session_id} didn't finish"))
+                })??;
+            match event.kind {
+                PromptEventKind::CommandStart { command } => {
+                    started = Some((command, event.prompt_id, event.received));
+                }
+                PromptEventKind::CommandEnd { exit_status } => {
+                    let Some((_, prompt_id, start)) = &started else {
+                        continue;
+                    };
+                    if event.prompt_id.is_some() && event.prompt_id != *prompt_id {
+                        continue;
+                    }
+                    break (
+                        prompt_id.clone(),
+                        exit_status,
+                        event.received.duration_since(*start),
+                    );
+                }
+                PromptEventKind::Prompt { .. } => {}
+            }
+        };
+
+        let prompt = match &prompt_id {
+            Some(id) => self.get_prompt_by_id(session_id, id).await?,
+            None => self.get_prompt(session_id).await?,
+        };
+        let overflow = self.get_line_counts(session_id).await?.overflow;
+        let (output, output_truncated) = self.command_output(session_id, &prompt, overflow).await?;
+        Ok(CommandOutcome {
+            command: started.map(|(command, _, _)| command).unwrap_or_default(),
+            prompt_id,
+            exit_status,
+            duration,
+            output,
+            output_truncated,
+        })
+    }
+}
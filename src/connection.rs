@@ -2,12 +2,15 @@ use crate::error::{Error, Result};
 use crate::generated::api::*;
 use futures_util::{SinkExt, StreamExt};
 use protobuf::Message as ProtobufMessage;
+use std::collections::VecDeque;
 use tokio::net::UnixStream;
 use tokio_tungstenite::tungstenite::{Message, http::Request};
 use tokio_tungstenite::{WebSocketStream, client_async};
 
 pub struct ITerm2Connection {
     websocket: WebSocketStream<UnixStream>,
+    next_request_id: i64,
+    pub(crate) notifications: VecDeque<Notification>,
 }
 
 impl ITerm2Connection {
@@ -85,7 +88,11 @@ impl ITerm2Connection {
             )));
         }
 
-        Ok(Self { websocket })
+        Ok(Self {
+            websocket,
+            next_request_id: 1,
+            notifications: VecDeque::new(),
+        })
     }
 
     pub async fn send_message(&mut self, message: ClientOriginatedMessage) -> Result<()> {
@@ -110,6 +117,89 @@ impl ITerm2Connection {
         }
     }
 
+    /// Send a request and wait for its response.
+    ///
+    /// The request is tagged with a fresh `id` and messages are read until the response with the
+    /// matching `id` arrives. Notifications received in the meantime are queued and can be read
+    /// with `next_notification()`.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if iTerm2 reports the request as malformed
+    pub async fn call(
+        &mut self,
+        mut message: ClientOriginatedMessage,
+    ) -> Result<ServerOriginatedMessage> {
+        let id = self.next_request_id;
+        self.next_request_id += 1;
+        message.set_id(id);
+
+        self.send_message(message).await?;
+
+        loop {
+            let mut response = self.receive_message().await?;
+            if response.has_notification() {
+                self.queue_notification(response.take_notification());
+                continue;
+            }
+            if response.id() != id {
+                tracing::warn!("Ignoring response with unexpected id {}", response.id());
+                continue;
+            }
+            if response.has_error() {
+                return Err(Error::Api(response.error().to_string()));
+            }
+            return Ok(response);
+        }
+    }
+
+    /// Send several requests without waiting, then collect their responses.
+    ///
+    /// All requests are written before any response is read, so iTerm2 processes them as one
+    /// batch. Responses are returned in request order; each is `Err(Error::Api)` if iTerm2
+    /// rejected that request.
+    ///
+    /// # Errors
+    /// Returns an error if the connection fails while sending or receiving
+    pub async fn call_batch(
+        &mut self,
+        messages: Vec<ClientOriginatedMessage>,
+    ) -> Result<Vec<Result<ServerOriginatedMessage>>> {
+        let first_id = self.next_request_id;
+        let count = messages.len();
+        for mut message in messages {
+            message.set_id(self.next_request_id);
+            self.next_request_id += 1;
+            self.send_message(message).await?;
+        }
+
+        let mut responses: Vec<Option<Result<ServerOriginatedMessage>>> =
+            (0..count).map(|_| None).collect();
+        let mut remaining = count;
+        while remaining > 0 {
+            let mut response = self.receive_message().await?;
+            if response.has_notification() {
+                self.queue_notification(response.take_notification());
+                continue;
+            }
+            let slot = usize::try_from(response.id() - first_id)
+                .ok()
+                .and_then(|index| responses.get_mut(index))
+                .filter(|slot| slot.is_none());
+            let Some(slot) = slot else {
+                tracing::warn!("Ignoring response with unexpected id {}", response.id());
+                continue;
+            };
+            *slot = Some(if response.has_error() {
+                Err(Error::Api(response.error().to_string()))
+            } else {
+                Ok(response)
+            });
+            remaining -= 1;
+        }
+
+        Ok(responses.into_iter().flatten().collect())
+    }
+
     /// Create a new window with a single tab.
     ///
     /// This is equivalent to creating a new iTerm2 window. If you want to create
@@ -142,9 +232,7 @@ impl ITerm2Connection {
         let mut message = ClientOriginatedMessage::new();
         message.set_create_tab_request(request);
 
-        self.send_message(message).await?;
-
-        let response = self.receive_message().await?;
+        let response = self.call(message).await?;
 
         if response.has_create_tab_response() {
             let create_response = response.create_tab_response();
@@ -207,9 +295,7 @@ impl ITerm2Connection {
         let mut message = ClientOriginatedMessage::new();
         message.set_create_tab_request(request);
 
-        self.send_message(message).await?;
-
-        let response = self.receive_message().await?;
+        let response = self.call(message).await?;
 
         if response.has_create_tab_response() {
             let create_response = response.create_tab_response();
@@ -228,6 +314,123 @@ impl ITerm2Connection {
         }
     }
 
+    /// Create a tab from a fully populated `CreateTabRequest`.
+    ///
+    /// Use this when you need options the simpler methods don't expose, such as `tab_index` or
+    /// `custom_profile_properties`, or when you need the new window and tab IDs.
+    ///
+    /// # Returns
+    /// The `CreateTabResponse`, which carries the window, tab and session IDs
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if the tab could not be created
+    pub async fn create_tab_with(
+        &mut self,
+        request: CreateTabRequest,
+    ) -> Result<CreateTabResponse> {
+        let mut message = ClientOriginatedMessage::new();
+        message.set_create_tab_request(request);
+
+        let mut response = self.call(message).await?;
+
+        if response.has_create_tab_response() {
+            let create_response = response.take_create_tab_response();
+            if create_response.status() == create_tab_response::Status::OK {
+                Ok(create_response)
+            } else {
+                Err(Error::Api(format!(
+                    "Create tab failed: {:?}",
+                    create_response.status()
+                )))
+            }
+        } else {
+            Err(Error::Api("Expected create tab response".to_string()))
+        }
+    }
+
+    /// Split a session into two panes.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session to split
+    /// * `vertical` - `true` for a vertical divider (panes side by side), `false` for a
+    ///   horizontal divider (panes stacked)
+    /// * `profile_name` - Optional profile name to use for the new pane
+    ///
+    /// # Returns
+    /// The unique identifier of the new session
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist, or `Error::Api` if the pane
+    /// is too small to split
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let session = connection.create_window(None).await?;
+    /// let right = connection
+    ///     .split_pane(session.unique_identifier(), true, None)
+    ///     .await?;
+    /// connection.send_text(&right, "top\r").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn split_pane(
+        &mut self,
+        session_id: &str,
+        vertical: bool,
+        profile_name: Option<&str>,
+    ) -> Result<String> {
+        let mut request = SplitPaneRequest::new();
+        request.set_session(session_id.to_string());
+        request.set_split_direction(if vertical {
+            split_pane_request::SplitDirection::VERTICAL
+        } else {
+            split_pane_request::SplitDirection::HORIZONTAL
+        });
+        if let Some(profile) = profile_name {
+            request.set_profile_name(profile.to_string());
+        }
+
+        self.split_pane_with(request)
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| Error::Api("Split pane returned no session".to_string()))
+    }
+
+    /// Split panes using a fully populated `SplitPaneRequest`.
+    ///
+    /// # Returns
+    /// The unique identifiers of the new sessions
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist, or `Error::Api` if the split
+    /// fails
+    pub async fn split_pane_with(&mut self, request: SplitPaneRequest) -> Result<Vec<String>> {
+        let session_id = request.session().to_string();
+
+        let mut message = ClientOriginatedMessage::new();
+        message.set_split_pane_request(request);
+
+        let mut response = self.call(message).await?;
+
+        if response.has_split_pane_response() {
+            let split_response = response.take_split_pane_response();
+            match split_response.status() {
+                split_pane_response::Status::OK => Ok(split_response.session_id),
+                split_pane_response::Status::SESSION_NOT_FOUND => {
+                    Err(Error::SessionNotFound(session_id))
+                }
+                status => Err(Error::Api(format!("Split pane failed: {status:?}"))),
+            }
+        } else {
+            Err(Error::Api("Expected split pane response".to_string()))
+        }
+    }
+
     /// Send text to a specific session.
     ///
     /// This sends the specified text to the terminal session identified by `session_id`.
@@ -264,9 +467,7 @@ impl ITerm2Connection {
         let mut message = ClientOriginatedMessage::new();
         message.set_send_text_request(request);
 
-        self.send_message(message).await?;
-
-        let response = self.receive_message().await?;
+        let response = self.call(message).await?;
 
         if response.has_send_text_response() {
             let send_response = response.send_text_response();
@@ -283,6 +484,52 @@ impl ITerm2Connection {
         }
     }
 
+    /// Inject bytes into a session as if the running program had written them.
+    ///
+    /// Unlike `send_text()`, the data bypasses the program and is interpreted by the terminal
+    /// directly, so it can carry escape sequences such as OSC 9 notifications.
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// // Post a notification from the session
+    /// connection.inject("SESSION-ID", b"\x1b]9;Build finished\x07").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn inject(&mut self, session_id: &str, data: &[u8]) -> Result<()> {
+        let mut request = InjectRequest::new();
+        request.session_id.push(session_id.to_string());
+        request.set_data(data.to_vec());
+
+        let mut message = ClientOriginatedMessage::new();
+        message.set_inject_request(request);
+
+        let response = self.call(message).await?;
+
+        if !response.has_inject_response() {
+            return Err(Error::Api("Expected inject response".to_string()));
+        }
+        match response
+            .inject_response()
+            .status
+            .first()
+            .map(|s| s.enum_value())
+        {
+            Some(Ok(inject_response::Status::OK)) | None => Ok(()),
+            Some(Ok(inject_response::Status::SESSION_NOT_FOUND)) => {
+                Err(Error::SessionNotFound(session_id.to_string()))
+            }
+            Some(Err(status)) => Err(Error::Api(format!("Inject failed: {status}"))),
+        }
+    }
+
     /// List all available sessions.
     ///
     /// This returns a list of all sessions that are currently available. Note that
@@ -316,9 +563,7 @@ impl ITerm2Connection {
         let mut message = ClientOriginatedMessage::new();
         message.set_list_sessions_request(request);
 
-        self.send_message(message).await?;
-
-        let response = self.receive_message().await?;
+        let response = self.call(message).await?;
 
         if response.has_list_sessions_response() {
             let list_response = response.list_sessions_response();
@@ -369,9 +614,7 @@ impl ITerm2Connection {
         let mut message = ClientOriginatedMessage::new();
         message.set_list_sessions_request(request);
 
-        self.send_message(message).await?;
-
-        let response = self.receive_message().await?;
+        let response = self.call(message).await?;
 
         if response.has_list_sessions_response() {
             let list_response = response.list_sessions_response();
@@ -380,4 +623,119 @@ impl ITerm2Connection {
             Err(Error::Api("Expected list sessions response".to_string()))
         }
     }
+
+    /// Get the full window, tab and session hierarchy.
+    ///
+    /// Unlike `get_windows()`, this also includes buried sessions and keeps each tab's split
+    /// tree intact, so it can be used to work out which window a tab or session belongs to.
+    ///
+    /// # Returns
+    /// The complete `ListSessionsResponse`
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if the list operation fails
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let layout = connection.get_layout().await?;
+    ///
+    /// println!("{} windows, {} buried sessions", layout.windows.len(), layout.buried_sessions.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_layout(&mut self) -> Result<ListSessionsResponse> {
+        let request = ListSessionsRequest::new();
+
+        let mut message = ClientOriginatedMessage::new();
+        message.set_list_sessions_request(request);
+
+        let mut response = self.call(message).await?;
+
+        if response.has_list_sessions_response() {
+            Ok(response.take_list_sessions_response())
+        } else {
+            Err(Error::Api("Expected list sessions response".to_string()))
+        }
+    }
+
+    /// Get the current focus state of the application.
+    ///
+    /// iTerm2 answers with a set of focus notifications that together describe which window is
+    /// key, which tab is selected in every window and which session is active in every tab.
+    ///
+    /// # Returns
+    /// A vector of `FocusChangedNotification` describing the current focus
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if the focus request fails
+    pub async fn get_focus(&mut self) -> Result<Vec<FocusChangedNotification>> {
+        let request = FocusRequest::new();
+
+        let mut message = ClientOriginatedMessage::new();
+        message.set_focus_request(request);
+
+        let mut response = self.call(message).await?;
+
+        if response.has_focus_response() {
+            Ok(response.take_focus_response().notifications)
+        } else {
+            Err(Error::Api("Expected focus response".to_string()))
+        }
+    }
+
+    /// Select a tab and bring its window to the front.
+    ///
+    /// # Arguments
+    /// * `tab_id` - The ID of the tab to select
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if the tab doesn't exist or the activation fails
+    pub async fn activate_tab(&mut self, tab_id: &str) -> Result<()> {
+        let mut request = ActivateRequest::new();
+        request.set_tab_id(tab_id.to_string());
+        request.set_select_tab(true);
+        request.set_order_window_front(true);
+        self.activate(request).await
+    }
+
+    /// Make a session active, selecting its tab and bringing its window to the front.
+    ///
+    /// # Arguments
+    /// * `session_id` - The unique identifier of the session to activate
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if the session doesn't exist or the activation fails
+    pub async fn activate_session(&mut self, session_id: &str) -> Result<()> {
+        let mut request = ActivateRequest::new();
+        request.set_session_id(session_id.to_string());
+        request.set_select_tab(true);
+        request.set_select_session(true);
+        request.set_order_window_front(true);
+        self.activate(request).await
+    }
+
+    async fn activate(&mut self, request: ActivateRequest) -> Result<()> {
+        let mut message = ClientOriginatedMessage::new();
+        message.set_activate_request(request);
+
+        let response = self.call(message).await?;
+
+        if response.has_activate_response() {
+            let activate_response = response.activate_response();
+            if activate_response.status() == activate_response::Status::OK {
+                Ok(())
+            } else {
+                Err(Error::Api(format!(
+                    "Activate failed: {:?}",
+                    activate_response.status()
+                )))
+            }
+        } else {
+            Err(Error::Api("Expected activate response".to_string()))
+        }
+    }
 }
@@ -19,9 +19,54 @@ pub enum Error {
     #[error("UTF-8 error: {0}")]
     Utf8(#[from] std::string::FromUtf8Error),
 
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+
     #[error("Connection failed: {0}")]
     Connection(String),
 
     #[error("API error: {0}")]
     Api(String),
+
+    #[error("Window not found: {0}")]
+    WindowNotFound(String),
+
+    #[error("Tab not found: {0}")]
+    TabNotFound(String),
+
+    #[error("Session not found: {0}")]
+    SessionNotFound(String),
+
+    #[error("Session can't be restarted: {0}")]
+    SessionNotRestartable(String),
+
+    #[error("Shell integration is not installed in session {0}")]
+    ShellIntegrationUnavailable(String),
+
+    #[error("Prompt not found: {0}")]
+    PromptNotFound(String),
+
+    #[error("Arrangement not found: {0}")]
+    ArrangementNotFound(String),
+
+    #[error("Invalid tab assignment: {0}")]
+    InvalidAssignment(String),
+
+    #[error("Invalid tab layout: {0}")]
+    InvalidLayout(String),
+
+    #[error("Property {0} can't be set right now; iTerm2 will try again later")]
+    PropertyDeferred(String),
+
+    #[error("Property {0} can't be set in the current state")]
+    PropertyImpossible(String),
+
+    #[error("Setting property {0} failed")]
+    PropertyFailed(String),
+
+    #[error("Timed out: {0}")]
+    Timeout(String),
+
+    #[error("Workspace error: {0}")]
+    Workspace(String),
 }
@@ -0,0 +1,199 @@
+//! Waiting for output, in the style of `expect`.
+//!
+//! `Expect` remembers how far into a session's output it has read. Each `wait_for()` looks for
+//! a match after that point, re-reading the buffer whenever iTerm2 reports a screen update, and
+//! moves the point past the match. Together with `send_text()` this scripts interactive programs
+//! running in real panes.
+
+use crate::buffer::BufferRange;
+use crate::connection::ITerm2Connection;
+use crate::error::{Error, Result};
+use crate::generated::api::{Coord, NotificationType};
+use crate::notification::is_screen_update;
+use crate::search::{SearchMatch, SearchScope};
+use regex::Regex;
+use std::time::Duration;
+
+/// Reads a session's output from a starting point onward.
+#[derive(Debug, Clone)]
+pub struct Expect {
+    session_id: String,
+    /// Matches must start at or after this cell
+    position: Coord,
+}
+
+impl Expect {
+    /// Start reading from the beginning of the cursor's line.
+    ///
+    /// This subscribes the connection to screen updates for the session. Call `stop()` when
+    /// done. Only this session's screen updates are taken from the connection while waiting;
+    /// other notifications stay queued for other readers.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session to read. Use a real session ID, not `"active"`.
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::expect::Expect;
+    /// # use regex::Regex;
+    /// # use std::time::Duration;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let session_id = "SESSION-ID";
+    /// let timeout = Duration::from_secs(10);
+    ///
+    /// let mut expect = Expect::start(&mut connection, session_id).await?;
+    /// connection.send_text(session_id, "ssh build-host\n").await?;
+    /// expect.wait_for(&mut connection, &Regex::new(r"[Pp]assword:")?, timeout).await?;
+    /// connection.send_text(session_id, "hunter2\n").await?;
+    /// expect.wait_for(&mut connection, &Regex::new(r"\$ $")?, timeout).await?;
+    /// expect.stop(&mut connection).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn start(connection: &mut ITerm2Connection, session_id: &str) -> Result<Self> {
+        connection
+            .subscribe(NotificationType::NOTIFY_ON_SCREEN_UPDATE, Some(session_id))
+            .await?;
+        let buffer = connection
+            .get_buffer(session_id, BufferRange::Screen, false)
+            .await?;
+        let mut position = Coord::new();
+        position.set_x(0);
+        position.set_y(buffer.cursor.y());
+        Ok(Self {
+            session_id: session_id.to_string(),
+            position,
+        })
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Where the next match may start.
+    pub fn position(&self) -> &Coord {
+        &self.position
+    }
+
+    /// Wait until `regex` matches output after the current position.
+    ///
+    /// On success the position moves to the end of the match, so the same text isn't matched
+    /// twice. The timeout covers reading the buffer as well as waiting for screen updates.
+    ///
+    /// # Errors
+    /// Returns `Error::Timeout` if nothing matches within `timeout`, or
+    /// `Error::SessionNotFound` if the session closes
+    pub async fn wait_for(
+        &mut self,
+        connection: &mut ITerm2Connection,
+        regex: &Regex,
+        timeout: Duration,
+    ) -> Result<SearchMatch> {
+        tokio::time::timeout(timeout, self.wait(connection, regex))
+            .await
+            .map_err(|_| {
+                Error::Timeout(format!(
+                    "no match for /{regex}/ in session {}",
+                    self.session_id
+                ))
+            })?
+    }
+
+    async fn wait(
+        &mut self,
+        connection: &mut ITerm2Connection,
+        regex: &Regex,
+    ) -> Result<SearchMatch> {
+        loop {
+            // The search below sees everything these updates reported
+            connection.discard_notifications_where(|n| is_screen_update(n, &self.session_id));
+            if let Some(found) = self.find(connection, regex).await? {
+                self.position = found.range.end.clone().unwrap_or_default();
+                return Ok(found);
+            }
+
+            connection
+                .next_notification_where(|n| is_screen_update(n, &self.session_id))
+                .await?;
+        }
+    }
+
+    async fn find(
+        &self,
+        connection: &mut ITerm2Connection,
+        regex: &Regex,
+    ) -> Result<Option<SearchMatch>> {
+        let counts = connection.get_line_counts(&self.session_id).await?;
+        let end = counts.overflow + counts.history + counts.grid;
+        let first = self.position.y().max(counts.overflow);
+        let matches = connection
+            .search(&self.session_id, regex, SearchScope::Lines(first, end))
+            .await?;
+        Ok(matches.into_iter().find(|found| {
+            let start = &found.range.start;
+            (start.y(), start.x()) >= (self.position.y(), self.position.x())
+        }))
+    }
+
+    /// Unsubscribe from the session's screen updates and discard any that are still queued.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if unsubscribing fails
+    pub async fn stop(self, connection: &mut ITerm2Connection) -> Result<()> {
+        let stopped = connection
+            .unsubscribe(
+                NotificationType::NOTIFY_ON_SCREEN_UPDATE,
+                Some(&self.session_id),
+            )
+            .await;
+        connection.discard_notifications_where(|n| is_screen_update(n, &self.session_id));
+        stopped
+    }
+}
+
+impl ITerm2Connection {
+    /// Wait until `regex` matches a session's output, from the cursor's line onward.
+    ///
+    /// This is a one-shot form of [`Expect`]. To match several prompts in turn without
+    /// re-matching earlier output, keep an `Expect` instead.
+    ///
+    /// # Errors
+    /// Returns `Error::Timeout` if nothing matches within `timeout`, or
+    /// `Error::SessionNotFound` if the session doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use regex::Regex;
+    /// # use std::time::Duration;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let ready = Regex::new(r"Listening on port (\d+)")?;
+    /// let found = connection
+    ///     .wait_for("SESSION-ID", &ready, Duration::from_secs(30))
+    ///     .await?;
+    /// println!("Server ready: {}", found.text);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn wait_for(
+        &mut self,
+        session_id: &str,
+        regex: &Regex,
+        timeout: Duration,
+    ) -> Result<SearchMatch> {
+        let mut expect = Expect::start(self, session_id).await?;
+        let found = expect.wait_for(self, regex, timeout).await;
+        let stopped = expect.stop(self).await;
+        let found = found?;
+        stopped?;
+        Ok(found)
+    }
+}
@@ -0,0 +1,600 @@
+//! Rendering session contents as HTML, ANSI-escaped text and SVG.
+//!
+//! Exporters work on a `Buffer` read with styles and the `Palette` of the session it came from.
+//! HTML and SVG keep screen lines as they appear; ANSI output joins soft-wrapped lines so the
+//! text rewraps correctly in a terminal of a different width.
+
+use crate::buffer::{Buffer, BufferLine, BufferRange};
+use crate::cells::CellGrid;
+use crate::connection::ITerm2Connection;
+use crate::error::Result;
+use crate::generated::api::CellStyle;
+use crate::style::{CellAttributes, Palette, Rgb, decode_styles};
+use std::fmt::Write;
+
+/// Output format for `ITerm2Connection::export_session()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Html,
+    Ansi,
+    Svg,
+}
+
+/// SVG font size in pixels. Cells are 0.6em wide and 1.2em tall.
+const SVG_FONT_SIZE: f64 = 14.0;
+const SVG_CELL_WIDTH: f64 = SVG_FONT_SIZE * 0.6;
+const SVG_LINE_HEIGHT: f64 = SVG_FONT_SIZE * 1.2;
+const FONT_FAMILY: &str = "Menlo, Monaco, 'DejaVu Sans Mono', monospace";
+
+/// A stretch of cells on one line that share attributes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Run {
+    column: usize,
+    /// Number of cells covered, counting both halves of double-width characters
+    cells: usize,
+    text: String,
+    attributes: CellAttributes,
+}
+
+/// Split a line into runs of identically styled cells. Uninitialized cells become spaces.
+fn line_runs(line: &BufferLine, palette: &Palette) -> Vec<Run> {
+    let grid = CellGrid::from_line(line);
+    let mut attributes = decode_styles(&line.styles, palette);
+    let plain = CellAttributes::resolve(&CellStyle::new(), palette);
+    attributes.resize(grid.width(), plain);
+
+    let mut runs: Vec<Run> = Vec::new();
+    for (column, cell_attributes) in attributes.into_iter().enumerate().take(grid.width()) {
+        let wide_continuation = grid.is_wide_continuation(column);
+        match runs.last_mut() {
+            Some(run) if wide_continuation => run.cells += 1,
+            Some(run) if run.attributes == cell_attributes => {
+                run.cells += 1;
+                run.text.push_str(&grid.text_in(column..column + 1));
+            }
+            _ => runs.push(Run {
+                column,
+                cells: 1,
+                text: grid.text_in(column..column + 1),
+                attributes: cell_attributes,
+            }),
+        }
+    }
+    runs
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// URL schemes that exported HTML and SVG turn into links.
+const LINK_SCHEMES: [&str; 5] = ["http", "https", "mailto", "file", "ftp"];
+
+/// Whether a hyperlink printed by a program is safe to make clickable in an exported document.
+///
+/// Any program can print a hyperlink, so schemes that run code when clicked, such as
+/// `javascript:`, are shown as plain text.
+fn linkable(url: &str) -> bool {
+    url::Url::parse(url).is_ok_and(|url| LINK_SCHEMES.contains(&url.scheme()))
+}
+
+/// Render as a standalone HTML document.
+///
+/// Colors, bold, faint, italic, underline, strikethrough and hyperlinks are preserved. Only
+/// http, https, mailto, file and ftp hyperlinks become links.
+///
+/// # Example
+/// ```rust
+/// use iterm2_api::buffer::{Buffer, BufferLine};
+/// use iterm2_api::export::to_html;
+/// use iterm2_api::generated::api::CodePointsPerCell;
+/// use iterm2_api::style::Palette;
+///
+/// let mut cells = CodePointsPerCell::new();
+/// cells.set_repeats(5);
+/// let line = BufferLine {
+///     y: 0,
+///     text: "a < b".to_string(),
+///     soft_wrapped: false,
+///     code_points_per_cell: vec![cells],
+///     styles: Vec::new(),
+/// };
+/// let buffer = Buffer {
+///     lines: vec![line],
+///     cursor: Default::default(),
+///     range: Default::default(),
+/// };
+///
+/// let html = to_html(&buffer, &Palette::default());
+/// assert!(html.contains("a &lt; b"));
+/// ```
+pub fn to_html(buffer: &Buffer, palette: &Palette) -> String {
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<style>\n\
+         body {{ margin: 0; background: {bg}; }}\n\
+         pre {{ margin: 0; padding: 8px; font-family: {FONT_FAMILY}; color: {fg}; }}\n\
+         a {{ color: inherit; }}\n\
+         </style>\n</head>\n<body>\n<pre>",
+        bg = palette.background.hex(),
+        fg = palette.foreground.hex(),
+    );
+
+    for (i, line) in buffer.lines.iter().enumerate() {
+        if i > 0 {
+            html.push('\n');
+        }
+        for run in line_runs(line, palette) {
+            let text = escape_xml(&run.text);
+            let css = html_css(&run.attributes, palette);
+            let span = if css.is_empty() {
+                text
+            } else {
+                format!("<span style=\"{css}\">{text}</span>")
+            };
+            match &run.attributes.hyperlink {
+                Some(link) if linkable(&link.url) => {
+                    let _ = write!(html, "<a href=\"{}\">{span}</a>", escape_xml(&link.url));
+                }
+                _ => html.push_str(&span),
+            }
+        }
+    }
+
+    html.push_str("</pre>\n</body>\n</html>\n");
+    html
+}
+
+fn html_css(attributes: &CellAttributes, palette: &Palette) -> String {
+    let mut css = Vec::new();
+    let foreground = if attributes.invisible {
+        attributes.background
+    } else {
+        attributes.foreground
+    };
+    if foreground != palette.foreground {
+        css.push(format!("color: {}", foreground.hex()));
+    }
+    if attributes.background != palette.background {
+        css.push(format!("background-color: {}", attributes.background.hex()));
+    }
+    if attributes.bold {
+        css.push("font-weight: bold".to_string());
+    }
+    if attributes.faint {
+        css.push("opacity: 0.5".to_string());
+    }
+    if attributes.italic {
+        css.push("font-style: italic".to_string());
+    }
+    let decorations: Vec<&str> = [
+        (attributes.underline, "underline"),
+        (attributes.strikethrough, "line-through"),
+    ]
+    .into_iter()
+    .filter_map(|(set, decoration)| set.then_some(decoration))
+    .collect();
+    if !decorations.is_empty() {
+        css.push(format!("text-decoration: {}", decorations.join(" ")));
+        if let Some(color) = attributes.underline_color {
+            css.push(format!("text-decoration-color: {}", color.hex()));
+        }
+    }
+    css.join("; ")
+}
+
+/// Render as text with ANSI escape sequences that reproduce colors and attributes.
+///
+/// Colors are written as 24-bit SGR sequences, except the palette's default foreground and
+/// background, which are left to the terminal. Hyperlinks use OSC 8.
+pub fn to_ansi(buffer: &Buffer, palette: &Palette) -> String {
+    let mut ansi = String::new();
+    for line in &buffer.lines {
+        for run in line_runs(line, palette) {
+            ansi.push_str(&sgr(&run.attributes, palette));
+            match &run.attributes.hyperlink {
+                Some(link) => {
+                    let _ = write!(
+                        ansi,
+                        "{}{}\x1b]8;;\x1b\\",
+                        osc8_start(&link.url, &link.identifier),
+                        run.text
+                    );
+                }
+                None => ansi.push_str(&run.text),
+            }
+        }
+        ansi.push_str("\x1b[0m");
+        if !line.soft_wrapped {
+            ansi.push('\n');
+        }
+    }
+    ansi
+}
+
+/// The OSC 8 sequence that starts a hyperlink.
+///
+/// Control characters would end the sequence early, so they are removed from the URL and the
+/// identifier. The `id` parameter is left out when there is no identifier, and `:` and `;`,
+/// which separate parameters, are removed from it.
+fn osc8_start(url: &str, identifier: &str) -> String {
+    let url: String = url.chars().filter(|c| !c.is_control()).collect();
+    let identifier: String = identifier
+        .chars()
+        .filter(|c| !c.is_control() && *c != ':' && *c != ';')
+        .collect();
+    if identifier.is_empty() {
+        format!("\x1b]8;;{url}\x1b\\")
+    } else {
+        format!("\x1b]8;id={identifier};{url}\x1b\\")
+    }
+}
+
+fn sgr(attributes: &CellAttributes, palette: &Palette) -> String {
+    let mut codes = vec!["0".to_string()];
+    for (set, code) in [
+        (attributes.bold, "1"),
+        (attributes.faint, "2"),
+        (attributes.italic, "3"),
+        (attributes.underline, "4"),
+        (attributes.blink, "5"),
+        (attributes.invisible, "8"),
+        (attributes.strikethrough, "9"),
+    ] {
+        if set {
+            codes.push(code.to_string());
+        }
+    }
+    let rgb =
+        |prefix: u8, color: Rgb| format!("{prefix};2;{};{};{}", color.red, color.green, color.blue);
+    if attributes.foreground != palette.foreground {
+        codes.push(rgb(38, attributes.foreground));
+    }
+    if attributes.background != palette.background {
+        codes.push(rgb(48, attributes.background));
+    }
+    if attributes.underline
+        && let Some(color) = attributes.underline_color
+    {
+        codes.push(rgb(58, color));
+    }
+    format!("\x1b[{}m", codes.join(";"))
+}
+
+/// Render as an SVG image of the lines, like a screenshot.
+///
+/// Cells are laid out on a fixed grid, so columns line up as they do on screen. Hyperlinks are
+/// linked as in `to_html()`.
+pub fn to_svg(buffer: &Buffer, palette: &Palette) -> String {
+    let rows: Vec<Vec<Run>> = buffer
+        .lines
+        .iter()
+        .map(|line| line_runs(line, palette))
+        .collect();
+    let columns = rows
+        .iter()
+        .filter_map(|runs| runs.last())
+        .map(|run| run.column + run.cells)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let width = columns as f64 * SVG_CELL_WIDTH;
+    let height = rows.len().max(1) as f64 * SVG_LINE_HEIGHT;
+
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" \
+         viewBox=\"0 0 {width} {height}\" font-family=\"{FONT_FAMILY}\" \
+         font-size=\"{SVG_FONT_SIZE}\">"
+    );
+    let _ = writeln!(
+        svg,
+        "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>",
+        palette.background.hex()
+    );
+
+    for (row, runs) in rows.iter().enumerate() {
+        let top = row as f64 * SVG_LINE_HEIGHT;
+        let baseline = top + SVG_FONT_SIZE;
+        for run in runs {
+            let x = run.column as f64 * SVG_CELL_WIDTH;
+            let attributes = &run.attributes;
+            if attributes.background != palette.background {
+                let _ = writeln!(
+                    svg,
+                    "<rect x=\"{x}\" y=\"{top}\" width=\"{}\" height=\"{SVG_LINE_HEIGHT}\" fill=\"{}\"/>",
+                    run.cells as f64 * SVG_CELL_WIDTH,
+                    attributes.background.hex()
+                );
+            }
+            if attributes.invisible || run.text.trim().is_empty() {
+                continue;
+            }
+
+            let mut text_attributes = format!(" fill=\"{}\"", attributes.foreground.hex());
+            if attributes.bold {
+                text_attributes.push_str(" font-weight=\"bold\"");
+            }
+            if attributes.italic {
+                text_attributes.push_str(" font-style=\"italic\"");
+            }
+            if attributes.faint {
+                text_attributes.push_str(" opacity=\"0.5\"");
+            }
+            let decorations: Vec<&str> = [
+                (attributes.underline, "underline"),
+                (attributes.strikethrough, "line-through"),
+            ]
+            .into_iter()
+            .filter_map(|(set, decoration)| set.then_some(decoration))
+            .collect();
+            if !decorations.is_empty() {
+                let _ = write!(
+                    text_attributes,
+                    " text-decoration=\"{}\"",
+                    decorations.join(" ")
+                );
+            }
+            let text = format!(
+                "<text x=\"{x}\" y=\"{baseline}\" xml:space=\"preserve\"{text_attributes}>{}</text>",
+                escape_xml(&run.text)
+            );
+            match &attributes.hyperlink {
+                Some(link) if linkable(&link.url) => {
+                    let _ = writeln!(svg, "<a href=\"{}\">{text}</a>", escape_xml(&link.url));
+                }
+                _ => {
+                    let _ = writeln!(svg, "{text}");
+                }
+            }
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+impl ITerm2Connection {
+    /// Read part of a session with styles and render it.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session to export
+    /// * `range` - Which lines to export
+    /// * `format` - The output format
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::buffer::BufferRange;
+    /// # use iterm2_api::export::ExportFormat;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let html = connection
+    ///     .export_session("active", BufferRange::Trailing(500), ExportFormat::Html)
+    ///     .await?;
+    /// std::fs::write("session.html", html)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn export_session(
+        &mut self,
+        session_id: &str,
+        range: BufferRange,
+        format: ExportFormat,
+    ) -> Result<String> {
+        let palette = self.get_palette(session_id).await?;
+        let buffer = self.get_buffer(session_id, range, true).await?;
+        Ok(match format {
+            ExportFormat::Html => to_html(&buffer, &palette),
+            ExportFormat::Ansi => to_ansi(&buffer, &palette),
+            ExportFormat::Svg => to_svg(&buffer, &palette),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generated::api::CodePointsPerCell;
+
+    fn run(num_code_points: i32, repeats: i32) -> CodePointsPerCell {
+        let mut run = CodePointsPerCell::new();
+        run.set_num_code_points(num_code_points);
+        run.set_repeats(repeats);
+        run
+    }
+
+    fn line(text: &str, cells: Vec<CodePointsPerCell>, styles: Vec<CellStyle>) -> BufferLine {
+        BufferLine {
+            y: 0,
+            text: text.to_string(),
+            soft_wrapped: false,
+            code_points_per_cell: cells,
+            styles,
+        }
+    }
+
+    fn buffer(lines: Vec<BufferLine>) -> Buffer {
+        Buffer {
+            lines,
+            cursor: Default::default(),
+            range: Default::default(),
+        }
+    }
+
+    fn styled(repeats: u32, edit: impl FnOnce(&mut CellStyle)) -> CellStyle {
+        let mut style = CellStyle::new();
+        style.set_repeats(repeats);
+        edit(&mut style);
+        style
+    }
+
+    fn linked(repeats: u32, url: &str, identifier: &str) -> CellStyle {
+        styled(repeats, |style| {
+            let link = style.url.mut_or_insert_default();
+            link.set_url(url.to_string());
+            link.set_identifier(identifier.to_string());
+        })
+    }
+
+    #[test]
+    fn runs_group_cells_and_cover_wide_characters() {
+        // "ok" in red, then "日" (two cells) and "!" in the default style
+        let line = line(
+            "ok日!",
+            vec![run(1, 3), run(0, 1), run(1, 1)],
+            vec![styled(2, |style| style.set_fgStandard(1)), CellStyle::new()],
+        );
+        let palette = Palette::default();
+        let runs = line_runs(&line, &palette);
+        assert_eq!(runs.len(), 2);
+        assert_eq!((runs[0].column, runs[0].cells), (0, 2));
+        assert_eq!(runs[0].text, "ok");
+        assert_eq!(runs[0].attributes.foreground, palette.ansi[1]);
+        // Cells without a style use the default
+        assert_eq!((runs[1].column, runs[1].cells), (2, 3));
+        assert_eq!(runs[1].text, "日!");
+    }
+
+    #[test]
+    fn wide_continuation_follows_a_wide_character() {
+        let grid = CellGrid::decode("日a", &[run(1, 1), run(0, 2), run(1, 1)]);
+        assert!(!grid.is_wide_continuation(0));
+        assert!(grid.is_wide_continuation(1));
+        assert!(!grid.is_wide_continuation(2));
+        assert!(!grid.is_wide_continuation(3));
+    }
+
+    #[test]
+    fn html_escapes_and_styles() {
+        let buffer = buffer(vec![
+            line(
+                "<b>",
+                vec![run(1, 3)],
+                vec![styled(3, |style| {
+                    style.set_bold(true);
+                    style.set_underline(true);
+                    style.set_strikethrough(true);
+                })],
+            ),
+            line(
+                "x",
+                vec![run(1, 1)],
+                vec![linked(1, "https://a.test/?q=\"1\"", "")],
+            ),
+        ]);
+        let html = to_html(&buffer, &Palette::default());
+        assert!(html.contains(
+            "<span style=\"font-weight: bold; text-decoration: underline line-through\">\
+             &lt;b&gt;</span>\n"
+        ));
+        assert!(html.contains("<a href=\"https://a.test/?q=&quot;1&quot;\">x</a>"));
+        assert!(html.ends_with("</pre>\n</body>\n</html>\n"));
+    }
+
+    #[test]
+    fn only_safe_schemes_are_linked() {
+        assert!(linkable("https://example.com"));
+        assert!(linkable("mailto:a@example.com"));
+        assert!(linkable("file:///tmp/report.txt"));
+        assert!(!linkable("javascript:alert(1)"));
+        assert!(!linkable("JavaScript:alert(1)"));
+        assert!(!linkable(" java\tscript:alert(1)"));
+        assert!(!linkable("data:text/html,<script>alert(1)</script>"));
+        assert!(!linkable("not a url"));
+
+        let buffer = buffer(vec![line(
+            "x",
+            vec![run(1, 1)],
+            vec![linked(1, "javascript:alert(1)", "")],
+        )]);
+        let html = to_html(&buffer, &Palette::default());
+        assert!(!html.contains("<a "));
+        assert!(html.contains("<pre>x</pre>"));
+        let svg = to_svg(&buffer, &Palette::default());
+        assert!(!svg.contains("<a "));
+        assert!(!svg.contains("javascript"));
+    }
+
+    #[test]
+    fn ansi_joins_wrapped_lines_and_resets() {
+        let mut first = line(
+            "ab",
+            vec![run(1, 2)],
+            vec![styled(2, |style| {
+                style.set_bold(true);
+                style.set_bgStandard(4);
+            })],
+        );
+        first.soft_wrapped = true;
+        let second = line("c", vec![run(1, 1)], Vec::new());
+
+        let ansi = to_ansi(&buffer(vec![first, second]), &Palette::default());
+        assert_eq!(ansi, "\x1b[0;1;48;2;2;37;199mab\x1b[0m\x1b[0mc\x1b[0m\n");
+    }
+
+    #[test]
+    fn ansi_hyperlinks() {
+        let buffer = buffer(vec![line(
+            "ab",
+            vec![run(1, 2)],
+            vec![
+                linked(1, "https://a.test/\x1b]evil\x07", ""),
+                linked(1, "https://b.test", "id:7;x"),
+            ],
+        )]);
+        let ansi = to_ansi(&buffer, &Palette::default());
+        assert!(ansi.contains("\x1b]8;;https://a.test/]evil\x1b\\a\x1b]8;;\x1b\\"));
+        assert!(ansi.contains("\x1b]8;id=id7x;https://b.test\x1b\\b\x1b]8;;\x1b\\"));
+    }
+
+    #[test]
+    fn svg_lays_out_cells_on_a_grid() {
+        let buffer = buffer(vec![
+            line(
+                "a b",
+                vec![run(1, 3)],
+                vec![
+                    CellStyle::new(),
+                    CellStyle::new(),
+                    styled(1, |style| style.set_bgStandard(1)),
+                ],
+            ),
+            line("", Vec::new(), Vec::new()),
+        ]);
+        let svg = to_svg(&buffer, &Palette::default());
+        let width = 3.0 * SVG_CELL_WIDTH;
+        let height = 2.0 * SVG_LINE_HEIGHT;
+        assert!(svg.starts_with(&format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\""
+        )));
+        assert!(svg.contains(&format!(
+            "<rect x=\"{}\" y=\"0\" width=\"{SVG_CELL_WIDTH}\" height=\"{SVG_LINE_HEIGHT}\" \
+             fill=\"#c91b00\"/>",
+            2.0 * SVG_CELL_WIDTH
+        )));
+        assert!(svg.contains(">a </text>"));
+        assert!(svg.contains(&format!(
+            "<text x=\"{}\" y=\"{SVG_FONT_SIZE}\" xml:space=\"preserve\" fill=\"#c7c7c7\">b</text>",
+            2.0 * SVG_CELL_WIDTH
+        )));
+        assert!(svg.ends_with("</svg>\n"));
+    }
+}
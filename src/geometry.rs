@@ -0,0 +1,335 @@
+//! Window tiling and cascading.
+//!
+//! Frames use iTerm2's screen coordinates: `origin` is the bottom-left corner of a window and
+//! `y` grows upward. Layouts are computed for a caller-supplied screen rectangle, since the API
+//! doesn't report screen sizes. Windows are placed in reading order of their current position
+//! (top row first, then left to right) so that tiling moves each window as little as possible.
+
+use crate::connection::ITerm2Connection;
+use crate::error::Result;
+use crate::properties::WindowFrame;
+
+/// How to arrange windows within a screen rectangle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Tiling {
+    /// Rows of equal height. `columns` defaults to the smallest square grid that fits every
+    /// window; a short last row is stretched to the full width.
+    Grid { columns: Option<usize> },
+    /// One full-height column per window.
+    Columns,
+    /// One full-width row per window.
+    Rows,
+    /// Overlapping windows, each offset down and to the right of the previous one.
+    Cascade { offset: f64 },
+}
+
+impl Tiling {
+    /// Compute `count` frames within `screen`, in placement order.
+    ///
+    /// # Example
+    /// ```rust
+    /// use iterm2_api::geometry::Tiling;
+    /// use iterm2_api::properties::WindowFrame;
+    ///
+    /// let screen = WindowFrame::new(0.0, 0.0, 1200.0, 800.0);
+    /// let frames = Tiling::Grid { columns: None }.frames(&screen, 3);
+    ///
+    /// // Two on top, one stretched across the bottom
+    /// assert_eq!(frames[0], WindowFrame::new(0.0, 400.0, 600.0, 400.0));
+    /// assert_eq!(frames[1], WindowFrame::new(600.0, 400.0, 600.0, 400.0));
+    /// assert_eq!(frames[2], WindowFrame::new(0.0, 0.0, 1200.0, 400.0));
+    /// ```
+    pub fn frames(&self, screen: &WindowFrame, count: usize) -> Vec<WindowFrame> {
+        if count == 0 {
+            return Vec::new();
+        }
+        match *self {
+            Tiling::Grid { columns } => {
+                let columns = columns
+                    .unwrap_or_else(|| (count as f64).sqrt().ceil() as usize)
+                    .clamp(1, count);
+                grid(screen, count, columns)
+            }
+            Tiling::Columns => grid(screen, count, count),
+            Tiling::Rows => grid(screen, count, 1),
+            Tiling::Cascade { offset } => cascade(screen, count, offset),
+        }
+    }
+}
+
+fn grid(screen: &WindowFrame, count: usize, columns: usize) -> Vec<WindowFrame> {
+    let rows = count.div_ceil(columns);
+    let top = screen.origin.y + screen.size.height;
+    let mut frames = Vec::with_capacity(count);
+    for row in 0..rows {
+        let in_row = columns.min(count - row * columns);
+        // Round shared edges so neighbours meet exactly
+        let y_top = (top - screen.size.height * row as f64 / rows as f64).round();
+        let y_bottom = (top - screen.size.height * (row + 1) as f64 / rows as f64).round();
+        for column in 0..in_row {
+            let x_left = edge(screen.origin.x, screen.size.width, column, in_row);
+            let x_right = edge(screen.origin.x, screen.size.width, column + 1, in_row);
+            frames.push(WindowFrame::new(
+                x_left,
+                y_bottom,
+                x_right - x_left,
+                y_top - y_bottom,
+            ));
+        }
+    }
+    frames
+}
+
+fn edge(start: f64, length: f64, index: usize, parts: usize) -> f64 {
+    (start + length * index as f64 / parts as f64).round()
+}
+
+fn cascade(screen: &WindowFrame, count: usize, offset: f64) -> Vec<WindowFrame> {
+    let steps = (count - 1) as f64;
+    // Keep windows at least half the screen, shrinking the offset if they'd run off the edge
+    let width = (screen.size.width - offset * steps).max(screen.size.width / 2.0);
+    let height = (screen.size.height - offset * steps).max(screen.size.height / 2.0);
+    let offset = if count > 1 {
+        offset
+            .min((screen.size.width - width) / steps)
+            .min((screen.size.height - height) / steps)
+    } else {
+        0.0
+    };
+    let top = screen.origin.y + screen.size.height;
+    (0..count)
+        .map(|i| {
+            let shift = (offset * i as f64).round();
+            WindowFrame::new(
+                screen.origin.x + shift,
+                top - shift - height.round(),
+                width.round(),
+                height.round(),
+            )
+        })
+        .collect()
+}
+
+/// Order window frames top to bottom, then left to right.
+///
+/// A frame starts a new row unless its top edge is within half its height of the top edge of
+/// the row's first (highest) frame.
+pub fn reading_order<T>(windows: &mut [(T, WindowFrame)]) {
+    let top = |frame: &WindowFrame| frame.origin.y + frame.size.height;
+    windows.sort_by(|(_, a), (_, b)| top(b).total_cmp(&top(a)));
+
+    let mut start = 0;
+    while start < windows.len() {
+        let row_top = top(&windows[start].1);
+        let end = start
+            + windows[start..]
+                .iter()
+                .take_while(|(_, frame)| row_top - top(frame) <= frame.size.height / 2.0)
+                .count();
+        windows[start..end].sort_by(|(_, a), (_, b)| a.origin.x.total_cmp(&b.origin.x));
+        start = end;
+    }
+}
+
+/// The smallest rectangle containing every frame, or `None` if there are none.
+///
+/// Useful as a screen rectangle when the actual screen size isn't known.
+pub fn bounding_frame<'a>(
+    frames: impl IntoIterator<Item = &'a WindowFrame>,
+) -> Option<WindowFrame> {
+    frames.into_iter().fold(None, |bounds, frame| {
+        let (left, bottom) = (frame.origin.x, frame.origin.y);
+        let (right, top) = (left + frame.size.width, bottom + frame.size.height);
+        Some(match bounds {
+            None => *frame,
+            Some(b) => {
+                let x = b.origin.x.min(left);
+                let y = b.origin.y.min(bottom);
+                WindowFrame::new(
+                    x,
+                    y,
+                    (b.origin.x + b.size.width).max(right) - x,
+                    (b.origin.y + b.size.height).max(top) - y,
+                )
+            }
+        })
+    })
+}
+
+impl ITerm2Connection {
+    /// Get the current frame of every window, in layout order.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if the request fails
+    pub async fn window_frames(&mut self) -> Result<Vec<(String, WindowFrame)>> {
+        let layout = self.get_layout().await?;
+        Ok(layout
+            .windows
+            .iter()
+            .map(|window| {
+                (
+                    window.window_id().to_string(),
+                    WindowFrame::from(&*window.frame),
+                )
+            })
+            .collect())
+    }
+
+    /// Arrange all windows within a screen rectangle.
+    ///
+    /// Frames are computed from the windows' current positions and applied in one batch.
+    ///
+    /// # Arguments
+    /// * `screen` - The area to fill, in screen points
+    /// * `tiling` - How to arrange the windows
+    ///
+    /// # Returns
+    /// The window IDs with the frames that were applied, in placement order
+    ///
+    /// # Errors
+    /// Returns `Error::PropertyImpossible` if a window can't be moved (for example because it is
+    /// full screen)
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::geometry::Tiling;
+    /// # use iterm2_api::properties::WindowFrame;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let screen = WindowFrame::new(0.0, 0.0, 1920.0, 1055.0);
+    /// connection
+    ///     .tile_windows(&screen, Tiling::Grid { columns: None })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn tile_windows(
+        &mut self,
+        screen: &WindowFrame,
+        tiling: Tiling,
+    ) -> Result<Vec<(String, WindowFrame)>> {
+        let mut windows = self.window_frames().await?;
+        reading_order(&mut windows);
+
+        let targets = tiling.frames(screen, windows.len());
+        let placed: Vec<(String, WindowFrame)> = windows
+            .into_iter()
+            .map(|(window_id, _)| window_id)
+            .zip(targets)
+            .collect();
+        let frames: Vec<(&str, WindowFrame)> = placed
+            .iter()
+            .map(|(window_id, frame)| (window_id.as_str(), *frame))
+            .collect();
+        self.set_window_frames(&frames).await?;
+        Ok(placed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generated::api::Frame;
+
+    fn frame(x: i32, y: i32, width: i32, height: i32) -> WindowFrame {
+        let mut frame = Frame::new();
+        frame.origin.mut_or_insert_default().set_x(x);
+        frame.origin.mut_or_insert_default().set_y(y);
+        frame.size.mut_or_insert_default().set_width(width);
+        frame.size.mut_or_insert_default().set_height(height);
+        WindowFrame::from(&frame)
+    }
+
+    #[test]
+    fn grid_rounds_shared_edges() {
+        let screen = frame(0, 0, 1000, 600);
+        let frames = Tiling::Grid { columns: Some(3) }.frames(&screen, 3);
+        assert_eq!(
+            frames,
+            vec![
+                frame(0, 0, 333, 600),
+                frame(333, 0, 334, 600),
+                frame(667, 0, 333, 600),
+            ]
+        );
+    }
+
+    #[test]
+    fn grid_defaults_to_a_square() {
+        let screen = frame(100, 50, 900, 900);
+        let frames = Tiling::Grid { columns: None }.frames(&screen, 5);
+        assert_eq!(frames.len(), 5);
+        // 3 columns, 2 rows; the short bottom row is stretched
+        assert_eq!(frames[0], frame(100, 500, 300, 450));
+        assert_eq!(frames[2], frame(700, 500, 300, 450));
+        assert_eq!(frames[3], frame(100, 50, 450, 450));
+        assert_eq!(frames[4], frame(550, 50, 450, 450));
+
+        let frames = Tiling::Grid { columns: Some(10) }.frames(&screen, 2);
+        assert_eq!(frames, Tiling::Columns.frames(&screen, 2));
+        assert!(Tiling::Rows.frames(&screen, 0).is_empty());
+    }
+
+    #[test]
+    fn columns_and_rows() {
+        let screen = frame(0, 0, 800, 600);
+        assert_eq!(
+            Tiling::Columns.frames(&screen, 2),
+            vec![frame(0, 0, 400, 600), frame(400, 0, 400, 600)]
+        );
+        assert_eq!(
+            Tiling::Rows.frames(&screen, 2),
+            vec![frame(0, 300, 800, 300), frame(0, 0, 800, 300)]
+        );
+    }
+
+    #[test]
+    fn cascade_stays_on_screen() {
+        let screen = frame(0, 0, 800, 600);
+        let frames = Tiling::Cascade { offset: 30.0 }.frames(&screen, 3);
+        assert_eq!(
+            frames,
+            vec![
+                frame(0, 60, 740, 540),
+                frame(30, 30, 740, 540),
+                frame(60, 0, 740, 540),
+            ]
+        );
+
+        // Too many windows for the offset: windows keep half the screen and the offset shrinks
+        let frames = Tiling::Cascade { offset: 100.0 }.frames(&screen, 5);
+        assert_eq!(frames[0], frame(0, 300, 400, 300));
+        assert_eq!(frames[4], frame(300, 0, 400, 300));
+
+        assert_eq!(
+            Tiling::Cascade { offset: 30.0 }.frames(&screen, 1),
+            vec![screen]
+        );
+    }
+
+    #[test]
+    fn reading_order_groups_rows() {
+        let mut windows = vec![
+            ("bottom-right", frame(500, 0, 400, 300)),
+            ("top-right", frame(500, 420, 400, 300)),
+            ("bottom-left", frame(0, 10, 400, 300)),
+            // Slightly lower than top-right but within half its height, so on the same row
+            ("top-left", frame(0, 300, 400, 400)),
+        ];
+        reading_order(&mut windows);
+        let order: Vec<&str> = windows.iter().map(|(name, _)| *name).collect();
+        assert_eq!(
+            order,
+            vec!["top-left", "top-right", "bottom-left", "bottom-right"]
+        );
+    }
+
+    #[test]
+    fn bounding_frame_covers_all() {
+        let frames = [frame(10, 20, 100, 100), frame(-50, 60, 100, 200)];
+        assert_eq!(bounding_frame(&frames), Some(frame(-50, 20, 160, 240)));
+        assert_eq!(bounding_frame(&[]), None);
+    }
+}
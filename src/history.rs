@@ -0,0 +1,164 @@
+//! Command history with output, from shell integration prompts.
+//!
+//! Each prompt iTerm2 recorded becomes a `CommandRecord` holding the command, where it ran, how
+//! it exited and the text it printed. Records serialize to JSON, one per line with
+//! `to_json_lines()`, for attaching transcripts to reports.
+
+use crate::connection::ITerm2Connection;
+use crate::error::{Error, Result};
+use crate::prompt::{Prompt, PromptState, prompt_from_response, prompt_request};
+use crate::selection::SubSelection;
+use serde::{Deserialize, Serialize};
+
+/// A command entered at a shell prompt and its output.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommandRecord {
+    pub prompt_id: String,
+    pub command: Option<String>,
+    pub working_directory: Option<String>,
+    pub state: PromptState,
+    /// Set once the command has finished
+    pub exit_status: Option<i32>,
+    pub output: String,
+    /// Whether the start of the output was dropped from scrollback history
+    pub output_truncated: bool,
+}
+
+/// Serialize records as JSON Lines: one JSON object per line, each line ending in `\n`.
+///
+/// # Errors
+/// Returns `Error::Json` if a record can't be serialized
+///
+/// # Example
+/// ```rust
+/// use iterm2_api::history::{CommandRecord, to_json_lines};
+/// use iterm2_api::prompt::PromptState;
+///
+/// let record = CommandRecord {
+///     prompt_id: "7C1F".to_string(),
+///     command: Some("make test".to_string()),
+///     working_directory: Some("/src/app".to_string()),
+///     state: PromptState::Finished,
+///     exit_status: Some(2),
+///     output: "FAILED tests/io.rs\nerror: 1 test failed".to_string(),
+///     output_truncated: false,
+/// };
+///
+/// let jsonl = to_json_lines(&[record.clone(), record])?;
+/// assert_eq!(jsonl.lines().count(), 2);
+/// assert!(jsonl.starts_with(r#"{"prompt_id":"7C1F","command":"make test""#));
+/// assert!(jsonl.contains(r#""state":"finished","exit_status":2"#));
+/// # Ok::<(), iterm2_api::Error>(())
+/// ```
+pub fn to_json_lines(records: &[CommandRecord]) -> Result<String> {
+    let mut jsonl = String::new();
+    for record in records {
+        jsonl.push_str(&serde_json::to_string(record)?);
+        jsonl.push('\n');
+    }
+    Ok(jsonl)
+}
+
+impl ITerm2Connection {
+    /// Read a session's command history with each command's output.
+    ///
+    /// Prompts are fetched together, then each command's output is read from the buffer.
+    /// Prompts that disappear from history while being read are skipped.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session. `"all"` is not accepted.
+    /// * `first` - Start at this prompt ID instead of the oldest prompt
+    /// * `last` - End at this prompt ID instead of the newest prompt
+    ///
+    /// # Returns
+    /// Records oldest first
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::history::to_json_lines;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let history = connection.command_history("SESSION-ID", None, None).await?;
+    /// std::fs::write("transcript.jsonl", to_json_lines(&history)?)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn command_history(
+        &mut self,
+        session_id: &str,
+        first: Option<&str>,
+        last: Option<&str>,
+    ) -> Result<Vec<CommandRecord>> {
+        let ids = self.list_prompts(session_id, first, last).await?;
+        let messages = ids
+            .iter()
+            .map(|id| prompt_request(session_id, Some(id)))
+            .collect();
+        let responses = self.call_batch(messages).await?;
+        let overflow = self.get_line_counts(session_id).await?.overflow;
+
+        let mut records = Vec::new();
+        for (id, response) in ids.iter().zip(responses) {
+            let prompt = match response.and_then(|r| prompt_from_response(r, session_id, Some(id)))
+            {
+                Ok(prompt) => prompt,
+                Err(Error::PromptNotFound(_)) => continue,
+                Err(e) => return Err(e),
+            };
+
+            let (output, output_truncated) =
+                self.command_output(session_id, &prompt, overflow).await?;
+
+            records.push(CommandRecord {
+                prompt_id: prompt.unique_id,
+                command: prompt.command,
+                working_directory: prompt.working_directory,
+                state: prompt.state,
+                exit_status: prompt.exit_status,
+                output,
+                output_truncated,
+            });
+        }
+        Ok(records)
+    }
+
+    /// Read the text a prompt's command printed.
+    ///
+    /// # Arguments
+    /// * `overflow` - The number of lines dropped from history, from `get_line_counts()`
+    ///
+    /// # Returns
+    /// The output, and whether its start was dropped from scrollback history
+    pub(crate) async fn command_output(
+        &mut self,
+        session_id: &str,
+        prompt: &Prompt,
+        overflow: i64,
+    ) -> Result<(String, bool)> {
+        let Some(mut range) = prompt.output_range.clone() else {
+            return Ok((String::new(), false));
+        };
+        if (range.start.y(), range.start.x()) >= (range.end.y(), range.end.x()) {
+            return Ok((String::new(), false));
+        }
+
+        if range.end.y() < overflow {
+            return Ok((String::new(), true));
+        }
+        let truncated = range.start.y() < overflow;
+        if truncated {
+            let start = range.start.mut_or_insert_default();
+            start.set_y(overflow);
+            start.set_x(0);
+        }
+        let output = self
+            .sub_selection_text(session_id, &SubSelection::new(range))
+            .await?;
+        Ok((output, truncated))
+    }
+}
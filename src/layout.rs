@@ -0,0 +1,727 @@
+//! Resizing split panes with `SetTabLayoutRequest`.
+//!
+//! iTerm2 only accepts a new layout if its split tree has exactly the same shape as the live one
+//! and the pane sizes of every split still add up to the same total. `LayoutEditor` starts from
+//! the live tree, adjusts grid sizes while rebalancing siblings to keep those totals, and
+//! validates the result before it is submitted.
+
+use crate::connection::ITerm2Connection;
+use crate::error::{Error, Result};
+use crate::generated::api::split_tree_node::split_tree_link::Child;
+use crate::generated::api::*;
+use crate::tree;
+
+/// A pane dimension, measured in grid cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    /// Columns. Panes separated by vertical dividers share the width.
+    Width,
+    /// Rows. Panes separated by horizontal dividers share the height.
+    Height,
+}
+
+impl Axis {
+    /// The axis along which the children of `node` are laid out.
+    pub(crate) fn of(node: &SplitTreeNode) -> Self {
+        if node.vertical() {
+            Axis::Width
+        } else {
+            Axis::Height
+        }
+    }
+}
+
+/// An editable copy of a tab's split tree.
+///
+/// # Example
+/// ```rust,no_run
+/// # use iterm2_api::ITerm2Connection;
+/// # use iterm2_api::layout::Axis;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut connection = ITerm2Connection::connect().await?;
+/// let windows = connection.get_windows().await?;
+/// let tab_id = windows[0].tabs[0].tab_id().to_string();
+///
+/// let mut editor = connection.edit_tab_layout(&tab_id).await?;
+/// let session_id = editor.session_ids()[0].clone();
+/// editor.set_fraction(&session_id, Axis::Width, 0.3)?;
+/// connection.set_tab_layout(&editor).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayoutEditor {
+    tab_id: String,
+    root: SplitTreeNode,
+    original: SplitTreeNode,
+}
+
+impl LayoutEditor {
+    /// Start editing a split tree.
+    pub fn new(tab_id: impl Into<String>, root: SplitTreeNode) -> Self {
+        Self {
+            tab_id: tab_id.into(),
+            original: root.clone(),
+            root,
+        }
+    }
+
+    /// Start editing the split tree of a tab in `layout`.
+    ///
+    /// # Errors
+    /// Returns `Error::TabNotFound` if the layout has no such tab
+    pub fn from_layout(layout: &ListSessionsResponse, tab_id: &str) -> Result<Self> {
+        let (_, tab) =
+            tree::find_tab(layout, tab_id).ok_or_else(|| Error::TabNotFound(tab_id.to_string()))?;
+        Ok(Self::new(tab_id, (*tab.root).clone()))
+    }
+
+    /// The ID of the tab being edited.
+    pub fn tab_id(&self) -> &str {
+        &self.tab_id
+    }
+
+    /// The edited split tree.
+    pub fn root(&self) -> &SplitTreeNode {
+        &self.root
+    }
+
+    /// The IDs of all panes, in depth-first order.
+    pub fn session_ids(&self) -> Vec<String> {
+        tree::sessions(&self.root)
+            .into_iter()
+            .map(|session| session.unique_identifier().to_string())
+            .collect()
+    }
+
+    /// The current grid size of a pane, as `(width, height)`.
+    pub fn size(&self, session_id: &str) -> Option<(i32, i32)> {
+        tree::sessions(&self.root)
+            .into_iter()
+            .find(|session| session.unique_identifier() == session_id)
+            .map(|session| (session.grid_size.width(), session.grid_size.height()))
+    }
+
+    /// Set the size of a pane along an axis, in grid cells.
+    ///
+    /// The size is taken from or given to the pane's siblings in the nearest split along `axis`,
+    /// in proportion to their current sizes, so the total is unchanged. The requested size is
+    /// clamped so every sibling keeps at least one cell.
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the pane isn't in this tab, or `Error::InvalidLayout`
+    /// if no split runs along `axis` (the pane already spans the whole tab in that dimension) or
+    /// the split is too small to give every pane a cell
+    ///
+    /// # Example
+    /// ```
+    /// use iterm2_api::generated::api::{SessionSummary, SplitTreeNode};
+    /// use iterm2_api::generated::api::split_tree_node::SplitTreeLink;
+    /// use iterm2_api::layout::{Axis, LayoutEditor};
+    ///
+    /// // Three side-by-side panes, 40 + 40 + 40 columns wide
+    /// let mut root = SplitTreeNode::new();
+    /// root.set_vertical(true);
+    /// for id in ["a", "b", "c"] {
+    ///     let mut session = SessionSummary::new();
+    ///     session.set_unique_identifier(id.to_string());
+    ///     session.grid_size.mut_or_insert_default().set_width(40);
+    ///     session.grid_size.mut_or_insert_default().set_height(30);
+    ///     let mut link = SplitTreeLink::new();
+    ///     link.set_session(session);
+    ///     root.links.push(link);
+    /// }
+    ///
+    /// let mut editor = LayoutEditor::new("1", root);
+    /// editor.set_size("a", Axis::Width, 60)?;
+    /// assert_eq!(editor.size("a"), Some((60, 30)));
+    /// assert_eq!(editor.size("b"), Some((30, 30)));
+    /// assert_eq!(editor.size("c"), Some((30, 30)));
+    /// editor.validate()?;
+    ///
+    /// editor.equalize();
+    /// assert_eq!(editor.size("a"), Some((40, 30)));
+    /// # Ok::<(), iterm2_api::Error>(())
+    /// ```
+    pub fn set_size(&mut self, session_id: &str, axis: Axis, cells: i32) -> Result<()> {
+        let (node, index) = self.enclosing_split(session_id, axis)?;
+        let extents: Vec<i32> = node
+            .links
+            .iter()
+            .map(|link| extent(link.child.as_ref(), axis))
+            .collect();
+        let total: i32 = extents.iter().sum();
+        let others = extents.len() as i32 - 1;
+        if total - others < 1 {
+            return Err(Error::InvalidLayout(format!(
+                "{} panes don't fit in {total} cells along {axis:?}",
+                extents.len()
+            )));
+        }
+        let cells = cells.clamp(1, total - others);
+
+        let weights: Vec<i32> = extents
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != index)
+            .map(|(_, extent)| *extent)
+            .collect();
+        let mut shares = distribute(total - cells, &weights).into_iter();
+        for (i, link) in node.links.iter_mut().enumerate() {
+            let share = if i == index {
+                cells
+            } else {
+                shares.next().unwrap_or(1)
+            };
+            set_extent(link.child.as_mut(), axis, share);
+        }
+        Ok(())
+    }
+
+    /// Set the size of a pane as a fraction of its enclosing split along `axis`.
+    ///
+    /// For example, `set_fraction(id, Axis::Width, 0.3)` makes the pane 30% as wide as the
+    /// panes it shares its row with.
+    ///
+    /// # Errors
+    /// See `set_size()`
+    pub fn set_fraction(&mut self, session_id: &str, axis: Axis, fraction: f64) -> Result<()> {
+        let (node, _) = self.enclosing_split(session_id, axis)?;
+        let total: i32 = node
+            .links
+            .iter()
+            .map(|link| extent(link.child.as_ref(), axis))
+            .sum();
+        let cells = (f64::from(total) * fraction).round() as i32;
+        self.set_size(session_id, axis, cells)
+    }
+
+    /// Divide a split's space between its children in the given proportions.
+    ///
+    /// # Arguments
+    /// * `path` - Link indexes leading from the root to the split; empty for the root itself
+    /// * `weights` - One relative weight per child, e.g. `[1.0, 2.0]` for a third and two thirds
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidLayout` if the path doesn't lead to a split or the number of
+    /// weights doesn't match its children
+    pub fn set_proportions(&mut self, path: &[usize], weights: &[f64]) -> Result<()> {
+        let mut node = &mut self.root;
+        for &index in path {
+            match node
+                .links
+                .get_mut(index)
+                .and_then(|link| link.child.as_mut())
+            {
+                Some(Child::Node(child)) => node = child,
+                _ => {
+                    return Err(Error::InvalidLayout(format!("no split at path {path:?}")));
+                }
+            }
+        }
+        if node.links.len() != weights.len() {
+            return Err(Error::InvalidLayout(format!(
+                "split at path {path:?} has {} panes but {} weights were given",
+                node.links.len(),
+                weights.len()
+            )));
+        }
+
+        let axis = Axis::of(node);
+        let total: i32 = node
+            .links
+            .iter()
+            .map(|link| extent(link.child.as_ref(), axis))
+            .sum();
+        let weights: Vec<i32> = weights
+            .iter()
+            .map(|weight| (weight.max(0.0) * 1000.0).round() as i32)
+            .collect();
+        let shares = distribute(total, &weights);
+        for (link, share) in node.links.iter_mut().zip(shares) {
+            set_extent(link.child.as_mut(), axis, share);
+        }
+        Ok(())
+    }
+
+    /// Give the children of every split an equal share of its space.
+    pub fn equalize(&mut self) {
+        equalize(&mut self.root);
+    }
+
+    /// Check that the edited tree is one iTerm2 will accept.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidLayout` if the tree's shape changed, a split's total size changed,
+    /// the children of a split differ in the other dimension, or a pane is smaller than one cell
+    pub fn validate(&self) -> Result<()> {
+        for axis in [Axis::Width, Axis::Height] {
+            let (original, edited) = (
+                node_extent(&self.original, axis),
+                node_extent(&self.root, axis),
+            );
+            if original != edited {
+                return Err(Error::InvalidLayout(format!(
+                    "root: tab is {edited} cells along {axis:?} instead of {original}"
+                )));
+            }
+        }
+        validate(&self.original, &self.root, "root")
+    }
+
+    /// Build the request that applies this layout.
+    pub fn to_request(&self) -> SetTabLayoutRequest {
+        let mut request = SetTabLayoutRequest::new();
+        request.set_tab_id(self.tab_id.clone());
+        request.root = protobuf::MessageField::some(self.root.clone());
+        request
+    }
+
+    /// Find the split that lays out `session_id` (or its ancestor) along `axis`, and the index of
+    /// the link leading to it.
+    fn enclosing_split(
+        &mut self,
+        session_id: &str,
+        axis: Axis,
+    ) -> Result<(&mut SplitTreeNode, usize)> {
+        let path = path_to(&self.root, session_id)
+            .ok_or_else(|| Error::SessionNotFound(session_id.to_string()))?;
+
+        // Walk down recording the deepest node splitting along `axis`.
+        let mut found = None;
+        let mut node = &self.root;
+        for (depth, &index) in path.iter().enumerate() {
+            if Axis::of(node) == axis && node.links.len() > 1 {
+                found = Some(depth);
+            }
+            match &node.links[index].child {
+                Some(Child::Node(child)) => node = child,
+                _ => break,
+            }
+        }
+        let depth = found.ok_or_else(|| {
+            Error::InvalidLayout(format!(
+                "session {session_id} has no sibling along {axis:?}"
+            ))
+        })?;
+
+        let mut node = &mut self.root;
+        for &index in &path[..depth] {
+            match &mut node.links[index].child {
+                Some(Child::Node(child)) => node = child,
+                _ => unreachable!("path leads through nodes"),
+            }
+        }
+        Ok((node, path[depth]))
+    }
+}
+
+impl ITerm2Connection {
+    /// Fetch a tab's split tree for editing.
+    ///
+    /// # Errors
+    /// Returns `Error::TabNotFound` if the tab doesn't exist
+    pub async fn edit_tab_layout(&mut self, tab_id: &str) -> Result<LayoutEditor> {
+        let layout = self.get_layout().await?;
+        LayoutEditor::from_layout(&layout, tab_id)
+    }
+
+    /// Validate and apply an edited layout.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidLayout` if local validation fails or iTerm2 reports `WRONG_TREE` or
+    /// `INVALID_SIZE` (for example because panes were split or closed since the tree was
+    /// fetched), or `Error::TabNotFound` if the tab no longer exists
+    pub async fn set_tab_layout(&mut self, editor: &LayoutEditor) -> Result<()> {
+        editor.validate()?;
+
+        let mut message = ClientOriginatedMessage::new();
+        message.set_set_tab_layout_request(editor.to_request());
+
+        let response = self.call(message).await?;
+
+        if response.has_set_tab_layout_response() {
+            match response.set_tab_layout_response().status() {
+                set_tab_layout_response::Status::OK => Ok(()),
+                set_tab_layout_response::Status::BAD_TAB_ID => {
+                    Err(Error::TabNotFound(editor.tab_id().to_string()))
+                }
+                status => Err(Error::InvalidLayout(format!(
+                    "iTerm2 rejected the layout: {status:?}"
+                ))),
+            }
+        } else {
+            Err(Error::Api("Expected set tab layout response".to_string()))
+        }
+    }
+
+    /// Resize a pane along one axis, rebalancing its siblings.
+    ///
+    /// This fetches the pane's tab layout, applies `LayoutEditor::set_size()` and submits it.
+    ///
+    /// # Errors
+    /// See `LayoutEditor::set_size()` and `set_tab_layout()`
+    pub async fn resize_session(&mut self, session_id: &str, axis: Axis, cells: i32) -> Result<()> {
+        let layout = self.get_layout().await?;
+        let (_, tab, _) = tree::find_session(&layout, session_id)
+            .ok_or_else(|| Error::SessionNotFound(session_id.to_string()))?;
+        let mut editor = LayoutEditor::from_layout(&layout, tab.tab_id())?;
+        editor.set_size(session_id, axis, cells)?;
+        self.set_tab_layout(&editor).await
+    }
+
+    /// Give every pane of a tab an equal share of its split.
+    ///
+    /// # Errors
+    /// See `set_tab_layout()`
+    pub async fn equalize_tab(&mut self, tab_id: &str) -> Result<()> {
+        let mut editor = self.edit_tab_layout(tab_id).await?;
+        editor.equalize();
+        self.set_tab_layout(&editor).await
+    }
+}
+
+/// The indexes of the links leading from `node` to `session_id`.
+fn path_to(node: &SplitTreeNode, session_id: &str) -> Option<Vec<usize>> {
+    node.links
+        .iter()
+        .enumerate()
+        .find_map(|(i, link)| match &link.child {
+            Some(Child::Session(session)) if session.unique_identifier() == session_id => {
+                Some(vec![i])
+            }
+            Some(Child::Node(child)) => path_to(child, session_id).map(|mut path| {
+                path.insert(0, i);
+                path
+            }),
+            _ => None,
+        })
+}
+
+/// The size of a subtree along `axis`.
+pub(crate) fn extent(child: Option<&Child>, axis: Axis) -> i32 {
+    match child {
+        Some(Child::Session(session)) => match axis {
+            Axis::Width => session.grid_size.width(),
+            Axis::Height => session.grid_size.height(),
+        },
+        Some(Child::Node(node)) => node_extent(node, axis),
+        None => 0,
+    }
+}
+
+/// The size of a split along `axis`.
+fn node_extent(node: &SplitTreeNode, axis: Axis) -> i32 {
+    let extents = node
+        .links
+        .iter()
+        .map(|link| extent(link.child.as_ref(), axis));
+    if Axis::of(node) == axis {
+        extents.sum()
+    } else {
+        extents.max().unwrap_or(0)
+    }
+}
+
+/// Resize a subtree along `axis`, scaling its children proportionally.
+fn set_extent(child: Option<&mut Child>, axis: Axis, value: i32) {
+    match child {
+        Some(Child::Session(session)) => {
+            let size = session.grid_size.mut_or_insert_default();
+            match axis {
+                Axis::Width => size.set_width(value),
+                Axis::Height => size.set_height(value),
+            }
+        }
+        Some(Child::Node(node)) => {
+            if Axis::of(node) == axis {
+                let weights: Vec<i32> = node
+                    .links
+                    .iter()
+                    .map(|link| extent(link.child.as_ref(), axis))
+                    .collect();
+                let shares = distribute(value, &weights);
+                for (link, share) in node.links.iter_mut().zip(shares) {
+                    set_extent(link.child.as_mut(), axis, share);
+                }
+            } else {
+                for link in &mut node.links {
+                    set_extent(link.child.as_mut(), axis, value);
+                }
+            }
+        }
+        None => {}
+    }
+}
+
+fn equalize(node: &mut SplitTreeNode) {
+    let axis = Axis::of(node);
+    let total: i32 = node
+        .links
+        .iter()
+        .map(|link| extent(link.child.as_ref(), axis))
+        .sum();
+    let shares = distribute(total, &vec![1; node.links.len()]);
+    for (link, share) in node.links.iter_mut().zip(shares) {
+        set_extent(link.child.as_mut(), axis, share);
+        if let Some(Child::Node(child)) = &mut link.child {
+            equalize(child);
+        }
+    }
+}
+
+/// Split `total` into integer shares proportional to `weights`, each at least one, summing
+/// exactly to `total` (largest remainder method).
+fn distribute(total: i32, weights: &[i32]) -> Vec<i32> {
+    if weights.is_empty() {
+        return Vec::new();
+    }
+    let count = weights.len() as i32;
+    let spare = (total - count).max(0);
+    let weight_sum: i64 = weights.iter().map(|w| i64::from((*w).max(0))).sum();
+
+    let mut shares: Vec<i32> = Vec::with_capacity(weights.len());
+    let mut remainders: Vec<(i64, usize)> = Vec::with_capacity(weights.len());
+    for (i, weight) in weights.iter().enumerate() {
+        let (share, remainder) = if weight_sum == 0 {
+            (i64::from(spare) / i64::from(count), 0)
+        } else {
+            let scaled = i64::from(spare) * i64::from((*weight).max(0));
+            (scaled / weight_sum, scaled % weight_sum)
+        };
+        shares.push(1 + share as i32);
+        remainders.push((remainder, i));
+    }
+
+    let mut leftover = total - shares.iter().sum::<i32>();
+    remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    for (_, i) in remainders.iter().cycle() {
+        if leftover <= 0 {
+            break;
+        }
+        shares[*i] += 1;
+        leftover -= 1;
+    }
+    shares
+}
+
+fn validate(original: &SplitTreeNode, edited: &SplitTreeNode, path: &str) -> Result<()> {
+    let invalid = |reason: String| Err(Error::InvalidLayout(format!("{path}: {reason}")));
+
+    if original.vertical() != edited.vertical() {
+        return invalid("split direction changed".to_string());
+    }
+    if original.links.len() != edited.links.len() {
+        return invalid("number of panes changed".to_string());
+    }
+
+    let axis = Axis::of(original);
+    let sum = |node: &SplitTreeNode| -> i32 {
+        node.links
+            .iter()
+            .map(|link| extent(link.child.as_ref(), axis))
+            .sum()
+    };
+    if sum(original) != sum(edited) {
+        return invalid(format!(
+            "sizes add up to {} instead of {}",
+            sum(edited),
+            sum(original)
+        ));
+    }
+
+    let across = match axis {
+        Axis::Width => Axis::Height,
+        Axis::Height => Axis::Width,
+    };
+    let expected = node_extent(edited, across);
+    for (i, link) in edited.links.iter().enumerate() {
+        let actual = extent(link.child.as_ref(), across);
+        if actual != expected {
+            return invalid(format!(
+                "pane {i} is {actual} cells along {across:?} instead of {expected}"
+            ));
+        }
+    }
+
+    for (i, (a, b)) in original.links.iter().zip(&edited.links).enumerate() {
+        match (&a.child, &b.child) {
+            (Some(Child::Session(a)), Some(Child::Session(b))) => {
+                if a.unique_identifier() != b.unique_identifier() {
+                    return invalid(format!("pane {i} is a different session"));
+                }
+                if b.grid_size.width() < 1 || b.grid_size.height() < 1 {
+                    return invalid(format!(
+                        "session {} is smaller than one cell",
+                        b.unique_identifier()
+                    ));
+                }
+            }
+            (Some(Child::Node(a)), Some(Child::Node(b))) => {
+                validate(a, b, &format!("{path}/{i}"))?;
+            }
+            _ => return invalid(format!("pane {i} changed between split and session")),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generated::api::split_tree_node::SplitTreeLink;
+
+    fn pane(id: &str, width: i32, height: i32) -> SplitTreeLink {
+        let mut session = SessionSummary::new();
+        session.set_unique_identifier(id.to_string());
+        let size = session.grid_size.mut_or_insert_default();
+        size.set_width(width);
+        size.set_height(height);
+        let mut link = SplitTreeLink::new();
+        link.set_session(session);
+        link
+    }
+
+    fn split(vertical: bool, links: Vec<SplitTreeLink>) -> SplitTreeNode {
+        let mut node = SplitTreeNode::new();
+        node.set_vertical(vertical);
+        node.links = links;
+        node
+    }
+
+    fn nested(node: SplitTreeNode) -> SplitTreeLink {
+        let mut link = SplitTreeLink::new();
+        link.set_node(node);
+        link
+    }
+
+    /// `a` on the left, `b` above `c` on the right.
+    fn sample() -> LayoutEditor {
+        let right = split(false, vec![pane("b", 40, 10), pane("c", 40, 20)]);
+        LayoutEditor::new("1", split(true, vec![pane("a", 80, 30), nested(right)]))
+    }
+
+    #[test]
+    fn set_size_rebalances_siblings() {
+        let mut editor = sample();
+        editor.set_size("a", Axis::Width, 60).unwrap();
+        assert_eq!(editor.size("a"), Some((60, 30)));
+        assert_eq!(editor.size("b"), Some((60, 10)));
+        assert_eq!(editor.size("c"), Some((60, 20)));
+        editor.validate().unwrap();
+
+        editor.set_size("c", Axis::Height, 25).unwrap();
+        assert_eq!(editor.size("b"), Some((60, 5)));
+        assert_eq!(editor.size("c"), Some((60, 25)));
+        editor.validate().unwrap();
+    }
+
+    #[test]
+    fn set_size_clamps_so_siblings_keep_a_cell() {
+        let mut editor = sample();
+        editor.set_size("b", Axis::Height, 100).unwrap();
+        assert_eq!(editor.size("b"), Some((40, 29)));
+        assert_eq!(editor.size("c"), Some((40, 1)));
+
+        editor.set_size("b", Axis::Height, -5).unwrap();
+        assert_eq!(editor.size("b"), Some((40, 1)));
+        assert_eq!(editor.size("c"), Some((40, 29)));
+    }
+
+    #[test]
+    fn set_size_rejects_splits_without_room() {
+        let root = split(true, vec![pane("a", 0, 30), pane("b", 0, 30)]);
+        let mut editor = LayoutEditor::new("1", root);
+        assert!(matches!(
+            editor.set_size("a", Axis::Width, 10),
+            Err(Error::InvalidLayout(_))
+        ));
+
+        let mut missing = SplitTreeNode::new();
+        missing.set_vertical(true);
+        for id in ["a", "b"] {
+            let mut session = SessionSummary::new();
+            session.set_unique_identifier(id.to_string());
+            let mut link = SplitTreeLink::new();
+            link.set_session(session);
+            missing.links.push(link);
+        }
+        let mut editor = LayoutEditor::new("1", missing);
+        assert!(matches!(
+            editor.set_size("a", Axis::Width, 10),
+            Err(Error::InvalidLayout(_))
+        ));
+    }
+
+    #[test]
+    fn set_size_needs_a_split_along_the_axis() {
+        let mut editor = sample();
+        assert!(matches!(
+            editor.set_size("a", Axis::Height, 10),
+            Err(Error::InvalidLayout(_))
+        ));
+        assert!(matches!(
+            editor.set_size("z", Axis::Width, 10),
+            Err(Error::SessionNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn set_proportions_and_equalize() {
+        let mut editor = sample();
+        editor.set_proportions(&[], &[1.0, 3.0]).unwrap();
+        // Every pane gets a cell first, then the rest is shared by weight
+        assert_eq!(editor.size("a"), Some((31, 30)));
+        assert_eq!(editor.size("b"), Some((89, 10)));
+        editor.set_proportions(&[1], &[1.0, 2.0]).unwrap();
+        assert_eq!(editor.size("b"), Some((89, 10)));
+        assert_eq!(editor.size("c"), Some((89, 20)));
+        assert!(editor.set_proportions(&[0], &[1.0]).is_err());
+        assert!(editor.set_proportions(&[], &[1.0]).is_err());
+
+        editor.equalize();
+        assert_eq!(editor.size("a"), Some((60, 30)));
+        assert_eq!(editor.size("b"), Some((60, 15)));
+        assert_eq!(editor.size("c"), Some((60, 15)));
+        editor.validate().unwrap();
+    }
+
+    #[test]
+    fn validate_rejects_changed_totals() {
+        let mut editor = sample();
+        set_extent(editor.root.links[0].child.as_mut(), Axis::Width, 70);
+        assert!(matches!(editor.validate(), Err(Error::InvalidLayout(_))));
+    }
+
+    #[test]
+    fn validate_rejects_mismatched_cross_extents() {
+        let mut editor = sample();
+        set_extent(editor.root.links[0].child.as_mut(), Axis::Height, 20);
+        assert!(matches!(editor.validate(), Err(Error::InvalidLayout(_))));
+
+        let mut editor = LayoutEditor::new("1", split(true, vec![pane("a", 40, 30)]));
+        set_extent(editor.root.links[0].child.as_mut(), Axis::Height, 20);
+        assert!(matches!(editor.validate(), Err(Error::InvalidLayout(_))));
+    }
+
+    #[test]
+    fn validate_rejects_changed_shape() {
+        let mut editor = sample();
+        editor.root.links.swap(0, 1);
+        assert!(matches!(editor.validate(), Err(Error::InvalidLayout(_))));
+
+        let mut editor = sample();
+        editor.root.set_vertical(false);
+        assert!(matches!(editor.validate(), Err(Error::InvalidLayout(_))));
+    }
+
+    #[test]
+    fn distribute_keeps_the_total() {
+        assert_eq!(distribute(10, &[1, 1, 1]), vec![4, 3, 3]);
+        assert_eq!(distribute(102, &[1, 3]), vec![26, 76]);
+        assert_eq!(distribute(3, &[0, 0, 100]), vec![1, 1, 1]);
+        assert_eq!(distribute(5, &[0, 0]), vec![3, 2]);
+        assert!(distribute(5, &[]).is_empty());
+    }
+}
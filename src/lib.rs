@@ -71,9 +71,34 @@
 //!
 //! See the `examples/` directory for more comprehensive usage examples.
 
+pub mod alerts;
+pub mod arrangement;
+pub mod buffer;
+pub mod cells;
+pub mod command;
 pub mod connection;
 pub mod error;
+pub mod expect;
+pub mod export;
 pub mod generated;
+pub mod geometry;
+pub mod history;
+pub mod layout;
+pub mod links;
+pub mod mru;
+pub mod notification;
+pub mod profile;
+pub mod prompt;
+pub mod properties;
+pub mod restart;
+pub mod search;
+pub mod selection;
+pub mod style;
+pub mod tabs;
+pub mod tail;
+pub mod tree;
+pub mod variables;
+pub mod workspace;
 
 pub use connection::ITerm2Connection;
 pub use error::{Error, Result};
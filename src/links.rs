@@ -0,0 +1,300 @@
+//! Extracting OSC 8 hyperlinks from session contents.
+
+use crate::buffer::{Buffer, BufferRange, coord};
+use crate::cells::CellGrid;
+use crate::connection::ITerm2Connection;
+use crate::error::Result;
+use crate::generated::api::CoordRange;
+use crate::style::expand_styles;
+
+/// A hyperlink found in the buffer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BufferHyperlink {
+    pub url: String,
+    /// The OSC 8 `id` parameter, or empty if the link had none
+    pub identifier: String,
+    /// The linked text as displayed
+    pub text: String,
+    /// The cells the link covers, using stable line numbers. Usable in a `SelectionRequest`.
+    pub range: CoordRange,
+}
+
+/// Find all hyperlinks in a buffer read with styles.
+///
+/// Adjacent cells with the same URL and identifier form one link, including where a soft-wrapped
+/// line continues on the next line. Links are returned in the order they appear.
+///
+/// # Example
+/// ```rust
+/// use iterm2_api::buffer::{Buffer, BufferLine};
+/// use iterm2_api::generated::api::{CellStyle, CodePointsPerCell};
+/// use iterm2_api::links::extract_hyperlinks;
+///
+/// let mut cells = CodePointsPerCell::new();
+/// cells.set_repeats(8);
+/// let mut plain = CellStyle::new();
+/// plain.set_repeats(4);
+/// let mut linked = CellStyle::new();
+/// linked.set_repeats(4);
+/// linked.url.mut_or_insert_default().set_url("https://ci.example.com/1".to_string());
+///
+/// let line = BufferLine {
+///     y: 7,
+///     text: "see job1".to_string(),
+///     soft_wrapped: false,
+///     code_points_per_cell: vec![cells],
+///     styles: vec![plain, linked],
+/// };
+/// let buffer = Buffer {
+///     lines: vec![line],
+///     cursor: Default::default(),
+///     range: Default::default(),
+/// };
+///
+/// let links = extract_hyperlinks(&buffer);
+/// assert_eq!(links.len(), 1);
+/// assert_eq!(links[0].text, "job1");
+/// assert_eq!((links[0].range.start.x(), links[0].range.end.x()), (4, 8));
+/// assert_eq!(links[0].range.start.y(), 7);
+/// ```
+pub fn extract_hyperlinks(buffer: &Buffer) -> Vec<BufferHyperlink> {
+    let mut links: Vec<BufferHyperlink> = Vec::new();
+    // Whether the last link ends exactly where the next cell in reading order begins
+    let mut open = false;
+
+    for line in &buffer.lines {
+        let grid = CellGrid::from_line(line);
+        let styles = expand_styles(&line.styles);
+        for (column, style) in styles.iter().enumerate().take(grid.width()) {
+            let Some(url) = style.url.as_ref() else {
+                open = false;
+                continue;
+            };
+            let text = grid.text_in(column..column + 1);
+            match links.last_mut() {
+                Some(link)
+                    if open && link.url == url.url() && link.identifier == url.identifier() =>
+                {
+                    if !grid.is_wide_continuation(column) {
+                        link.text.push_str(&text);
+                    }
+                    link.range.end = protobuf::MessageField::some(coord(column + 1, line.y));
+                }
+                _ => {
+                    let mut range = CoordRange::new();
+                    range.start = protobuf::MessageField::some(coord(column, line.y));
+                    range.end = protobuf::MessageField::some(coord(column + 1, line.y));
+                    links.push(BufferHyperlink {
+                        url: url.url().to_string(),
+                        identifier: url.identifier().to_string(),
+                        text,
+                        range,
+                    });
+                }
+            }
+            open = true;
+        }
+        // Only a link that reaches the end of a soft-wrapped line continues on the next one
+        open = open && line.soft_wrapped && styles.len() >= grid.width();
+    }
+    links
+}
+
+impl ITerm2Connection {
+    /// Find the hyperlinks in part of a session.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session to read
+    /// * `range` - Which lines to search
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::buffer::BufferRange;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let links = connection
+    ///     .get_hyperlinks("active", BufferRange::Trailing(1000))
+    ///     .await?;
+    /// if let Some(link) = links.iter().rev().find(|link| link.url.contains("/actions/runs/")) {
+    ///     println!("Last CI link: {}", link.url);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_hyperlinks(
+        &mut self,
+        session_id: &str,
+        range: BufferRange,
+    ) -> Result<Vec<BufferHyperlink>> {
+        let buffer = self.get_buffer(session_id, range, true).await?;
+        Ok(extract_hyperlinks(&buffer))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::buffer_from_response;
+    use crate::generated::api::line_contents::Continuation;
+    use crate::generated::api::*;
+
+    fn run(num_code_points: i32, repeats: i32) -> CodePointsPerCell {
+        let mut run = CodePointsPerCell::new();
+        run.set_num_code_points(num_code_points);
+        run.set_repeats(repeats);
+        run
+    }
+
+    fn style(repeats: u32, url: Option<(&str, &str)>) -> CellStyle {
+        let mut style = CellStyle::new();
+        style.set_repeats(repeats);
+        if let Some((url, identifier)) = url {
+            let link = style.url.mut_or_insert_default();
+            link.set_url(url.to_string());
+            link.set_identifier(identifier.to_string());
+        }
+        style
+    }
+
+    fn contents(
+        text: &str,
+        cells: Vec<CodePointsPerCell>,
+        styles: Vec<CellStyle>,
+        continuation: Continuation,
+    ) -> LineContents {
+        let mut contents = LineContents::new();
+        contents.set_text(text.to_string());
+        contents.code_points_per_cell = cells;
+        contents.style = styles;
+        contents.set_continuation(continuation);
+        contents
+    }
+
+    fn buffer(first: i64, lines: Vec<LineContents>) -> Buffer {
+        let mut response = GetBufferResponse::new();
+        response.set_status(get_buffer_response::Status::OK);
+        response
+            .windowed_coord_range
+            .mut_or_insert_default()
+            .coord_range
+            .mut_or_insert_default()
+            .start
+            .mut_or_insert_default()
+            .set_y(first);
+        response.contents = lines;
+        let mut message = ServerOriginatedMessage::new();
+        message.set_get_buffer_response(response);
+        buffer_from_response(message, "s1").unwrap()
+    }
+
+    fn span(link: &BufferHyperlink) -> ((i32, i64), (i32, i64)) {
+        let (start, end) = (&link.range.start, &link.range.end);
+        ((start.x(), start.y()), (end.x(), end.y()))
+    }
+
+    const DOCS: Option<(&str, &str)> = Some(("https://docs.example.com", ""));
+
+    #[test]
+    fn links_continue_across_soft_wraps() {
+        let buffer = buffer(
+            10,
+            vec![
+                contents(
+                    "see docs",
+                    vec![run(1, 8)],
+                    vec![style(4, None), style(4, DOCS)],
+                    Continuation::CONTINUATION_SOFT_EOL,
+                ),
+                contents(
+                    "ite now",
+                    vec![run(1, 7)],
+                    vec![style(3, DOCS), style(4, None)],
+                    Continuation::CONTINUATION_HARD_EOL,
+                ),
+            ],
+        );
+        let links = extract_hyperlinks(&buffer);
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].text, "docsite");
+        assert_eq!(span(&links[0]), ((4, 10), (3, 11)));
+    }
+
+    #[test]
+    fn hard_line_ends_and_other_urls_split_links() {
+        let other = Some(("https://docs.example.com", "2"));
+        let buffer = buffer(
+            0,
+            vec![
+                contents(
+                    "abcd",
+                    vec![run(1, 4)],
+                    vec![style(2, DOCS), style(2, other)],
+                    Continuation::CONTINUATION_HARD_EOL,
+                ),
+                contents(
+                    "ef",
+                    vec![run(1, 2)],
+                    vec![style(2, other)],
+                    Continuation::CONTINUATION_HARD_EOL,
+                ),
+            ],
+        );
+        let links = extract_hyperlinks(&buffer);
+        let found: Vec<(&str, &str)> = links
+            .iter()
+            .map(|link| (link.identifier.as_str(), link.text.as_str()))
+            .collect();
+        assert_eq!(found, [("", "ab"), ("2", "cd"), ("2", "ef")]);
+        assert_eq!(span(&links[1]), ((2, 0), (4, 0)));
+    }
+
+    #[test]
+    fn links_that_stop_before_the_wrap_do_not_continue() {
+        // The link ends a cell before the soft-wrapped line does
+        let buffer = buffer(
+            0,
+            vec![
+                contents(
+                    "ab ",
+                    vec![run(1, 3)],
+                    vec![style(2, DOCS), style(1, None)],
+                    Continuation::CONTINUATION_SOFT_EOL,
+                ),
+                contents(
+                    "cd",
+                    vec![run(1, 2)],
+                    vec![style(2, DOCS)],
+                    Continuation::CONTINUATION_HARD_EOL,
+                ),
+            ],
+        );
+        let texts: Vec<String> = extract_hyperlinks(&buffer)
+            .into_iter()
+            .map(|link| link.text)
+            .collect();
+        assert_eq!(texts, ["ab", "cd"]);
+    }
+
+    #[test]
+    fn wide_characters_cover_two_columns() {
+        // "x" then "日本" linked: each wide character is followed by an empty cell
+        let buffer = buffer(
+            3,
+            vec![contents(
+                "x日本",
+                vec![run(1, 1), run(1, 1), run(0, 1), run(1, 1), run(0, 1)],
+                vec![style(1, None), style(4, DOCS)],
+                Continuation::CONTINUATION_HARD_EOL,
+            )],
+        );
+        let links = extract_hyperlinks(&buffer);
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].text, "日本");
+        assert_eq!(span(&links[0]), ((1, 3), (5, 3)));
+    }
+}
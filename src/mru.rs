@@ -0,0 +1,416 @@
+//! Most-recently-used ordering of windows, tabs and sessions.
+//!
+//! `MruTracker` follows focus and layout notifications to keep, for every window, its tabs in
+//! the order they were last selected, and for every tab, its sessions in the order they were
+//! last active. This is the Rust counterpart of the Python `mrutabs` examples.
+
+use crate::connection::ITerm2Connection;
+use crate::error::Result;
+use crate::generated::api::focus_changed_notification::Event;
+use crate::generated::api::focus_changed_notification::window::WindowStatus;
+use crate::generated::api::*;
+use crate::tree;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Default)]
+pub struct MruTracker {
+    /// Window IDs, most recently focused first.
+    windows: Vec<String>,
+    /// Tab IDs per window, most recently selected first.
+    tabs: HashMap<String, Vec<String>>,
+    /// Session IDs per tab, most recently active first.
+    sessions: HashMap<String, Vec<String>>,
+    tab_windows: HashMap<String, String>,
+    session_tabs: HashMap<String, String>,
+}
+
+impl MruTracker {
+    /// Create a tracker and seed it with the current layout and focus.
+    ///
+    /// This subscribes the connection to focus and layout change notifications. Call `update()`
+    /// in a loop to keep the tracker current.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if subscribing or querying the current state fails
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::mru::MruTracker;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let mut tracker = MruTracker::start(&mut connection).await?;
+    ///
+    /// loop {
+    ///     tracker.update(&mut connection).await?;
+    ///     if let Some(window_id) = tracker.current_window() {
+    ///         println!("Tabs in MRU order: {:?}", tracker.tabs(window_id));
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn start(connection: &mut ITerm2Connection) -> Result<Self> {
+        connection
+            .subscribe(NotificationType::NOTIFY_ON_FOCUS_CHANGE, None)
+            .await?;
+        connection
+            .subscribe(NotificationType::NOTIFY_ON_LAYOUT_CHANGE, None)
+            .await?;
+
+        let layout = connection.get_layout().await?;
+        let mut tracker = Self::from_layout(&layout);
+        for notification in connection.get_focus().await? {
+            tracker.handle_focus(&notification);
+        }
+        Ok(tracker)
+    }
+
+    /// Create a tracker from a layout, using display order as the initial MRU order.
+    pub fn from_layout(layout: &ListSessionsResponse) -> Self {
+        let mut tracker = Self::default();
+        tracker.update_layout(layout);
+        tracker
+    }
+
+    /// Wait for the next focus or layout change notification and apply it.
+    ///
+    /// Other notifications stay queued on the connection.
+    ///
+    /// # Errors
+    /// Returns `Error::Connection` if the connection is closed
+    pub async fn update(&mut self, connection: &mut ITerm2Connection) -> Result<()> {
+        let notification = connection
+            .next_notification_where(|n| {
+                n.focus_changed_notification.is_some() || n.layout_changed_notification.is_some()
+            })
+            .await?;
+        self.handle_notification(&notification);
+        Ok(())
+    }
+
+    /// Apply a focus or layout change notification. Other notifications are ignored.
+    pub fn handle_notification(&mut self, notification: &Notification) {
+        if let Some(focus) = notification.focus_changed_notification.as_ref() {
+            self.handle_focus(focus);
+        }
+        if let Some(layout) = notification.layout_changed_notification.as_ref() {
+            self.update_layout(&layout.list_sessions_response);
+        }
+    }
+
+    /// Move the newly focused window, tab or session to the front of its MRU list.
+    pub fn handle_focus(&mut self, notification: &FocusChangedNotification) {
+        match &notification.event {
+            Some(Event::Window(window))
+                if window.window_status() == WindowStatus::TERMINAL_WINDOW_BECAME_KEY =>
+            {
+                touch(&mut self.windows, window.window_id());
+            }
+            Some(Event::SelectedTab(tab_id)) => {
+                if let Some(window_id) = self.tab_windows.get(tab_id)
+                    && let Some(tabs) = self.tabs.get_mut(window_id)
+                {
+                    touch(tabs, tab_id);
+                }
+            }
+            Some(Event::Session(session_id)) => {
+                if let Some(tab_id) = self.session_tabs.get(session_id)
+                    && let Some(sessions) = self.sessions.get_mut(tab_id)
+                {
+                    touch(sessions, session_id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Reconcile with a new layout.
+    ///
+    /// Closed windows, tabs and sessions are dropped, new ones are appended as least recently
+    /// used, and tabs or sessions that moved keep their relative order in their new home.
+    pub fn update_layout(&mut self, layout: &ListSessionsResponse) {
+        let mut tabs = HashMap::new();
+        let mut sessions = HashMap::new();
+        self.tab_windows.clear();
+        self.session_tabs.clear();
+
+        for window in &layout.windows {
+            let window_id = window.window_id().to_string();
+            let tab_ids: Vec<String> = window
+                .tabs
+                .iter()
+                .map(|tab| tab.tab_id().to_string())
+                .collect();
+
+            for tab in &window.tabs {
+                let tab_id = tab.tab_id().to_string();
+                let session_ids: Vec<String> = tree::tab_sessions(tab)
+                    .into_iter()
+                    .map(|session| session.unique_identifier().to_string())
+                    .collect();
+                for session_id in &session_ids {
+                    self.session_tabs.insert(session_id.clone(), tab_id.clone());
+                }
+                let previous = self.sessions.remove(&tab_id).unwrap_or_default();
+                sessions.insert(tab_id.clone(), reconcile(previous, session_ids));
+                self.tab_windows.insert(tab_id, window_id.clone());
+            }
+
+            let previous = self.tabs.remove(&window_id).unwrap_or_default();
+            tabs.insert(window_id, reconcile(previous, tab_ids));
+        }
+
+        let window_ids = layout
+            .windows
+            .iter()
+            .map(|window| window.window_id().to_string())
+            .collect();
+        self.windows = reconcile(std::mem::take(&mut self.windows), window_ids);
+        self.tabs = tabs;
+        self.sessions = sessions;
+    }
+
+    /// Window IDs, most recently focused first.
+    pub fn windows(&self) -> &[String] {
+        &self.windows
+    }
+
+    /// The most recently focused window.
+    pub fn current_window(&self) -> Option<&str> {
+        self.windows.first().map(String::as_str)
+    }
+
+    /// Tab IDs of a window, most recently selected first.
+    pub fn tabs(&self, window_id: &str) -> &[String] {
+        self.tabs
+            .get(window_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Session IDs of a tab, most recently active first.
+    pub fn sessions(&self, tab_id: &str) -> &[String] {
+        self.sessions
+            .get(tab_id)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// The tab that was selected in a window before the current one.
+    pub fn previous_tab(&self, window_id: &str) -> Option<&str> {
+        self.tabs(window_id).get(1).map(String::as_str)
+    }
+
+    /// The session that was active in a tab before the current one.
+    pub fn previous_session(&self, tab_id: &str) -> Option<&str> {
+        self.sessions(tab_id).get(1).map(String::as_str)
+    }
+
+    /// Select the previously selected tab of a window ("jump back").
+    ///
+    /// # Arguments
+    /// * `window_id` - The window to act on, or `None` for the most recently focused window
+    ///
+    /// # Returns
+    /// The ID of the tab that was selected, or `None` if the window has no previous tab
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if the activation fails
+    pub async fn switch_to_previous_tab(
+        &self,
+        connection: &mut ITerm2Connection,
+        window_id: Option<&str>,
+    ) -> Result<Option<String>> {
+        let Some(window_id) = window_id.or_else(|| self.current_window()) else {
+            return Ok(None);
+        };
+        let Some(tab_id) = self.previous_tab(window_id) else {
+            return Ok(None);
+        };
+        connection.activate_tab(tab_id).await?;
+        Ok(Some(tab_id.to_string()))
+    }
+
+    /// Activate the previously active session of a tab.
+    ///
+    /// # Returns
+    /// The ID of the session that was activated, or `None` if the tab has no previous session
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if the activation fails
+    pub async fn switch_to_previous_session(
+        &self,
+        connection: &mut ITerm2Connection,
+        tab_id: &str,
+    ) -> Result<Option<String>> {
+        let Some(session_id) = self.previous_session(tab_id) else {
+            return Ok(None);
+        };
+        connection.activate_session(session_id).await?;
+        Ok(Some(session_id.to_string()))
+    }
+
+    /// Reorder the tabs of a window so the most recently selected tab comes first.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if iTerm2 rejects the new order
+    pub async fn reorder_tabs(
+        &self,
+        connection: &mut ITerm2Connection,
+        window_id: &str,
+    ) -> Result<()> {
+        let mut assignment = reorder_tabs_request::Assignment::new();
+        assignment.set_window_id(window_id.to_string());
+        assignment.tab_ids = self.tabs(window_id).to_vec();
+        connection.reorder_tabs(vec![assignment]).await
+    }
+}
+
+/// Move `id` to the front of `list`, if present.
+fn touch(list: &mut Vec<String>, id: &str) {
+    if let Some(index) = list.iter().position(|item| item == id) {
+        let item = list.remove(index);
+        list.insert(0, item);
+    }
+}
+
+/// Keep the known IDs of `current` in `previous` order, followed by any new ones.
+fn reconcile(previous: Vec<String>, current: Vec<String>) -> Vec<String> {
+    let mut result: Vec<String> = previous
+        .into_iter()
+        .filter(|id| current.contains(id))
+        .collect();
+    for id in current {
+        if !result.contains(&id) {
+            result.push(id);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generated::api::focus_changed_notification::Window as FocusWindow;
+    use crate::generated::api::list_sessions_response::{Tab, Window};
+    use crate::generated::api::split_tree_node::SplitTreeLink;
+
+    /// Window ID and its tabs, each a tab ID and its session IDs.
+    type WindowSpec<'a> = (&'a str, &'a [(&'a str, &'a [&'a str])]);
+
+    fn layout(windows: &[WindowSpec]) -> ListSessionsResponse {
+        let mut layout = ListSessionsResponse::new();
+        for (window_id, tabs) in windows {
+            let mut window = Window::new();
+            window.set_window_id(window_id.to_string());
+            for (tab_id, session_ids) in *tabs {
+                let mut tab = Tab::new();
+                tab.set_tab_id(tab_id.to_string());
+                let root = tab.root.mut_or_insert_default();
+                for session_id in *session_ids {
+                    let mut session = SessionSummary::new();
+                    session.set_unique_identifier(session_id.to_string());
+                    let mut link = SplitTreeLink::new();
+                    link.set_session(session);
+                    root.links.push(link);
+                }
+                window.tabs.push(tab);
+            }
+            layout.windows.push(window);
+        }
+        layout
+    }
+
+    fn selected_tab(tab_id: &str) -> FocusChangedNotification {
+        let mut focus = FocusChangedNotification::new();
+        focus.set_selected_tab(tab_id.to_string());
+        focus
+    }
+
+    fn active_session(session_id: &str) -> FocusChangedNotification {
+        let mut focus = FocusChangedNotification::new();
+        focus.set_session(session_id.to_string());
+        focus
+    }
+
+    fn key_window(window_id: &str) -> FocusChangedNotification {
+        let mut window = FocusWindow::new();
+        window.set_window_id(window_id.to_string());
+        window.set_window_status(WindowStatus::TERMINAL_WINDOW_BECAME_KEY);
+        let mut focus = FocusChangedNotification::new();
+        focus.set_window(window);
+        focus
+    }
+
+    fn ids(ids: &[&str]) -> Vec<String> {
+        ids.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn reconcile_drops_closed_and_appends_new() {
+        assert_eq!(
+            reconcile(ids(&["c", "a", "b"]), ids(&["a", "b", "d", "c"])),
+            ids(&["c", "a", "b", "d"])
+        );
+        assert_eq!(
+            reconcile(ids(&["c", "a"]), ids(&["a", "e"])),
+            ids(&["a", "e"])
+        );
+        assert_eq!(reconcile(Vec::new(), ids(&["x", "y"])), ids(&["x", "y"]));
+    }
+
+    #[test]
+    fn focus_moves_to_front() {
+        let mut tracker = MruTracker::from_layout(&layout(&[
+            (
+                "w1",
+                &[("t1", &["s1"]), ("t2", &["s2", "s3"]), ("t3", &["s4"])],
+            ),
+            ("w2", &[("t4", &["s5"])]),
+        ]));
+        assert_eq!(tracker.windows(), ids(&["w1", "w2"]));
+        assert_eq!(tracker.tabs("w1"), ids(&["t1", "t2", "t3"]));
+
+        tracker.handle_focus(&selected_tab("t3"));
+        tracker.handle_focus(&selected_tab("t2"));
+        tracker.handle_focus(&active_session("s3"));
+        tracker.handle_focus(&key_window("w2"));
+        assert_eq!(tracker.current_window(), Some("w2"));
+        assert_eq!(tracker.tabs("w1"), ids(&["t2", "t3", "t1"]));
+        assert_eq!(tracker.previous_tab("w1"), Some("t3"));
+        assert_eq!(tracker.sessions("t2"), ids(&["s3", "s2"]));
+        assert_eq!(tracker.previous_session("t2"), Some("s2"));
+
+        // Unknown IDs are ignored
+        tracker.handle_focus(&selected_tab("t9"));
+        assert_eq!(tracker.tabs("w1"), ids(&["t2", "t3", "t1"]));
+    }
+
+    #[test]
+    fn layout_changes_keep_mru_order() {
+        let mut tracker = MruTracker::from_layout(&layout(&[
+            ("w1", &[("t1", &["s1"]), ("t2", &["s2"]), ("t3", &["s3"])]),
+            ("w2", &[("t4", &["s4"])]),
+        ]));
+        tracker.handle_focus(&selected_tab("t3"));
+        tracker.handle_focus(&selected_tab("t1"));
+
+        // t2 closes, t5 opens, and t3 moves to w2
+        tracker.update_layout(&layout(&[
+            ("w1", &[("t1", &["s1"]), ("t5", &["s5"])]),
+            ("w2", &[("t4", &["s4"]), ("t3", &["s3"])]),
+        ]));
+        assert_eq!(tracker.tabs("w1"), ids(&["t1", "t5"]));
+        assert_eq!(tracker.tabs("w2"), ids(&["t4", "t3"]));
+
+        tracker.handle_focus(&active_session("s3"));
+        tracker.handle_focus(&selected_tab("t3"));
+        assert_eq!(tracker.tabs("w2"), ids(&["t3", "t4"]));
+
+        tracker.update_layout(&layout(&[("w2", &[("t4", &["s4"])])]));
+        assert_eq!(tracker.windows(), ids(&["w2"]));
+        assert!(tracker.tabs("w1").is_empty());
+        assert!(tracker.sessions("t3").is_empty());
+    }
+}
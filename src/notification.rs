@@ -0,0 +1,236 @@
+use crate::connection::ITerm2Connection;
+use crate::error::{Error, Result};
+use crate::generated::api::*;
+
+/// Notifications kept for later `next_notification()` calls before the oldest are dropped.
+pub const MAX_QUEUED_NOTIFICATIONS: usize = 4096;
+
+impl ITerm2Connection {
+    /// Subscribe to a kind of notification.
+    ///
+    /// Notifications that use a session (keystrokes, screen updates, prompts, ...) are delivered
+    /// for `session_id`, which may also be `"all"`. The others ignore it, so pass `None`.
+    ///
+    /// # Arguments
+    /// * `notification_type` - The kind of notification to subscribe to
+    /// * `session_id` - The session to watch, if the notification type uses one
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if the session doesn't exist or iTerm2 rejects the subscription
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::generated::api::NotificationType;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// connection
+    ///     .subscribe(NotificationType::NOTIFY_ON_FOCUS_CHANGE, None)
+    ///     .await?;
+    ///
+    /// loop {
+    ///     let notification = connection.next_notification().await?;
+    ///     println!("{:?}", notification.focus_changed_notification);
+    /// }
+    /// # }
+    /// ```
+    pub async fn subscribe(
+        &mut self,
+        notification_type: NotificationType,
+        session_id: Option<&str>,
+    ) -> Result<()> {
+        let mut request = NotificationRequest::new();
+        request.set_notification_type(notification_type);
+        if let Some(session_id) = session_id {
+            request.set_session(session_id.to_string());
+        }
+        self.subscribe_with(request).await
+    }
+
+    /// Unsubscribe from a kind of notification.
+    ///
+    /// # Arguments
+    /// * `notification_type` - The kind of notification to unsubscribe from
+    /// * `session_id` - The session that was passed to `subscribe()`
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if there is no matching subscription
+    pub async fn unsubscribe(
+        &mut self,
+        notification_type: NotificationType,
+        session_id: Option<&str>,
+    ) -> Result<()> {
+        let mut request = NotificationRequest::new();
+        request.set_notification_type(notification_type);
+        request.set_subscribe(false);
+        if let Some(session_id) = session_id {
+            request.set_session(session_id.to_string());
+        }
+        self.send_notification_request(request).await
+    }
+
+    /// Subscribe using a fully populated `NotificationRequest`.
+    ///
+    /// Use this for notification types that take extra arguments, such as a
+    /// `PromptMonitorRequest` or a `VariableMonitorRequest`. The `subscribe` field is set for you.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if iTerm2 rejects the subscription
+    pub async fn subscribe_with(&mut self, mut request: NotificationRequest) -> Result<()> {
+        request.set_subscribe(true);
+        self.send_notification_request(request).await
+    }
+
+    /// Unsubscribe using a `NotificationRequest` like the one passed to `subscribe_with()`.
+    ///
+    /// The `subscribe` field is cleared for you.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if there is no matching subscription
+    pub async fn unsubscribe_with(&mut self, mut request: NotificationRequest) -> Result<()> {
+        request.set_subscribe(false);
+        self.send_notification_request(request).await
+    }
+
+    async fn send_notification_request(&mut self, request: NotificationRequest) -> Result<()> {
+        let mut message = ClientOriginatedMessage::new();
+        message.set_notification_request(request);
+
+        let response = self.call(message).await?;
+
+        if response.has_notification_response() {
+            let notification_response = response.notification_response();
+            if notification_response.status() == notification_response::Status::OK {
+                Ok(())
+            } else {
+                Err(Error::Api(format!(
+                    "Notification request failed: {:?}",
+                    notification_response.status()
+                )))
+            }
+        } else {
+            Err(Error::Api("Expected notification response".to_string()))
+        }
+    }
+
+    /// Wait for the next notification.
+    ///
+    /// Notifications that arrived while waiting for a response to another request are returned
+    /// first, in the order they were received. This takes notifications of every kind; when
+    /// several helpers share a connection, use `next_notification_where()` so that each only
+    /// takes its own.
+    ///
+    /// # Errors
+    /// Returns `Error::Connection` if the connection is closed
+    pub async fn next_notification(&mut self) -> Result<Notification> {
+        self.next_notification_where(|_| true).await
+    }
+
+    /// Wait for the next notification that `filter` accepts.
+    ///
+    /// Notifications the filter rejects are kept queued, in order, for the next call that
+    /// accepts them. Each notification is delivered once. If more than
+    /// `MAX_QUEUED_NOTIFICATIONS` are waiting, the oldest are dropped with a warning.
+    ///
+    /// # Errors
+    /// Returns `Error::Connection` if the connection is closed
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::generated::api::NotificationType;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// connection
+    ///     .subscribe(NotificationType::NOTIFY_ON_NEW_SESSION, None)
+    ///     .await?;
+    ///
+    /// let notification = connection
+    ///     .next_notification_where(|n| n.new_session_notification.is_some())
+    ///     .await?;
+    /// println!("{:?}", notification.new_session_notification);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn next_notification_where<F>(&mut self, mut filter: F) -> Result<Notification>
+    where
+        F: FnMut(&Notification) -> bool,
+    {
+        if let Some(index) = self.notifications.iter().position(&mut filter)
+            && let Some(notification) = self.notifications.remove(index)
+        {
+            return Ok(notification);
+        }
+
+        loop {
+            let mut message = self.receive_message().await?;
+            if !message.has_notification() {
+                tracing::warn!(
+                    "Dropping response with id {} that no request is waiting for",
+                    message.id()
+                );
+                continue;
+            }
+            let notification = message.take_notification();
+            if filter(&notification) {
+                return Ok(notification);
+            }
+            self.queue_notification(notification);
+        }
+    }
+
+    /// Remove queued notifications that `filter` accepts, without waiting for new ones.
+    ///
+    /// Use this to throw away notifications nobody will read any more, such as those of a
+    /// subscription that was just cancelled.
+    ///
+    /// # Returns
+    /// The number of notifications removed
+    pub fn discard_notifications_where<F>(&mut self, mut filter: F) -> usize
+    where
+        F: FnMut(&Notification) -> bool,
+    {
+        let queued = self.notifications.len();
+        self.notifications
+            .retain(|notification| !filter(notification));
+        queued - self.notifications.len()
+    }
+
+    /// Keep a notification for a later `next_notification()` call.
+    pub(crate) fn queue_notification(&mut self, notification: Notification) {
+        if self.notifications.len() >= MAX_QUEUED_NOTIFICATIONS {
+            self.notifications.pop_front();
+            tracing::warn!(
+                "More than {MAX_QUEUED_NOTIFICATIONS} notifications queued; dropping the oldest"
+            );
+        }
+        self.notifications.push_back(notification);
+    }
+}
+
+/// Whether `notification` is a screen update for `session_id`.
+pub(crate) fn is_screen_update(notification: &Notification, session_id: &str) -> bool {
+    notification
+        .screen_update_notification
+        .as_ref()
+        .is_some_and(|update| update.session() == session_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screen_updates_are_matched_by_session() {
+        let mut notification = Notification::new();
+        notification
+            .screen_update_notification
+            .mut_or_insert_default()
+            .set_session("s1".to_string());
+        assert!(is_screen_update(&notification, "s1"));
+        assert!(!is_screen_update(&notification, "s2"));
+        assert!(!is_screen_update(&Notification::new(), "s1"));
+    }
+}
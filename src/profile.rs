@@ -0,0 +1,169 @@
+//! Reading and changing the profile settings of a session.
+//!
+//! Each session has its own copy of its profile, which starts out as the profile it was created
+//! with and can be changed without affecting other sessions. Profile keys are the names used in
+//! iTerm2's preferences (for example `"Normal Font"` or `"Badge Text"`), and values are JSON.
+
+use crate::connection::ITerm2Connection;
+use crate::error::{Error, Result};
+use crate::generated::api::*;
+use serde_json::{Map, Value};
+
+impl ITerm2Connection {
+    /// Get properties of a session's profile as JSON.
+    ///
+    /// This reads the session's own copy of the profile, including changes made to the session
+    /// alone.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session whose profile to read
+    /// * `keys` - Profile keys such as `"Foreground Color"`, or empty for every property
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let properties = connection
+    ///     .get_profile_properties("active", &["Name", "Normal Font"])
+    ///     .await?;
+    /// println!("{properties:?}");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_profile_properties(
+        &mut self,
+        session_id: &str,
+        keys: &[&str],
+    ) -> Result<Map<String, Value>> {
+        let mut request = GetProfilePropertyRequest::new();
+        request.set_session(session_id.to_string());
+        request.keys = keys.iter().map(|key| key.to_string()).collect();
+
+        let mut message = ClientOriginatedMessage::new();
+        message.set_get_profile_property_request(request);
+
+        let mut response = self.call(message).await?;
+
+        if !response.has_get_profile_property_response() {
+            return Err(Error::Api(
+                "Expected get profile property response".to_string(),
+            ));
+        }
+        let property_response = response.take_get_profile_property_response();
+        match property_response.status() {
+            get_profile_property_response::Status::OK => property_response
+                .properties
+                .iter()
+                .map(|property| {
+                    Ok((
+                        property.key().to_string(),
+                        serde_json::from_str(property.json_value())?,
+                    ))
+                })
+                .collect(),
+            get_profile_property_response::Status::SESSION_NOT_FOUND => {
+                Err(Error::SessionNotFound(session_id.to_string()))
+            }
+            status => Err(Error::Api(format!(
+                "Get profile property failed: {status:?}"
+            ))),
+        }
+    }
+
+    /// Change profile properties of a session without modifying the underlying profile.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session to change. `"all"` and `"active"` are accepted.
+    /// * `properties` - Profile keys and their new values, e.g. `"Badge Text"`
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist, or `Error::Api` if a value
+    /// is malformed
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use serde_json::{Map, json};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let mut properties = Map::new();
+    /// properties.insert("Badge Text".to_string(), json!("deploying"));
+    /// connection.set_profile_properties("active", &properties).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_profile_properties(
+        &mut self,
+        session_id: &str,
+        properties: &Map<String, Value>,
+    ) -> Result<()> {
+        let response = self
+            .call(set_profile_property_message(session_id, properties)?)
+            .await?;
+
+        if !response.has_set_profile_property_response() {
+            return Err(Error::Api(
+                "Expected set profile property response".to_string(),
+            ));
+        }
+        match response.set_profile_property_response().status() {
+            set_profile_property_response::Status::OK => Ok(()),
+            set_profile_property_response::Status::SESSION_NOT_FOUND => {
+                Err(Error::SessionNotFound(session_id.to_string()))
+            }
+            status => Err(Error::Api(format!(
+                "Set profile property failed: {status:?}"
+            ))),
+        }
+    }
+}
+
+fn set_profile_property_message(
+    session_id: &str,
+    properties: &Map<String, Value>,
+) -> Result<ClientOriginatedMessage> {
+    let mut request = SetProfilePropertyRequest::new();
+    request.set_session(session_id.to_string());
+    for (key, value) in properties {
+        let mut assignment = set_profile_property_request::Assignment::new();
+        assignment.set_key(key.clone());
+        assignment.set_json_value(serde_json::to_string(value)?);
+        request.assignments.push(assignment);
+    }
+
+    let mut message = ClientOriginatedMessage::new();
+    message.set_set_profile_property_request(request);
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn assignments_are_json_encoded() {
+        let mut properties = Map::new();
+        properties.insert("Badge Text".to_string(), json!("deploying"));
+        properties.insert("Use Tab Color".to_string(), json!(true));
+
+        let message = set_profile_property_message("S1", &properties).unwrap();
+        let request = message.set_profile_property_request();
+        assert_eq!(request.session(), "S1");
+        let assignments: Vec<_> = request
+            .assignments
+            .iter()
+            .map(|a| (a.key(), a.json_value()))
+            .collect();
+        assert_eq!(
+            assignments,
+            [("Badge Text", "\"deploying\""), ("Use Tab Color", "true")]
+        );
+    }
+}
@@ -0,0 +1,413 @@
+//! Shell prompts recorded by shell integration.
+//!
+//! When shell integration is installed, iTerm2 remembers each prompt: where the prompt, the
+//! command and its output are in the buffer, the working directory, and the command's exit
+//! status once it finishes. Prompts are identified by a unique prompt ID.
+//!
+//! `PromptMonitor` follows prompts as they happen: a new prompt appearing, a command starting
+//! and a command ending.
+
+use crate::connection::ITerm2Connection;
+use crate::error::{Error, Result};
+use crate::generated::api::*;
+use futures_util::Stream;
+use serde::{Deserialize, Serialize};
+use std::time::{Instant, SystemTime};
+
+/// Where a prompt's command is in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptState {
+    /// The command hasn't been started yet
+    Editing,
+    Running,
+    Finished,
+}
+
+impl From<get_prompt_response::State> for PromptState {
+    fn from(state: get_prompt_response::State) -> Self {
+        match state {
+            get_prompt_response::State::EDITING => PromptState::Editing,
+            get_prompt_response::State::RUNNING => PromptState::Running,
+            get_prompt_response::State::FINISHED => PromptState::Finished,
+        }
+    }
+}
+
+/// A shell prompt and the command entered at it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prompt {
+    pub unique_id: String,
+    /// The prompt itself, e.g. `user@host ~ %`
+    pub prompt_range: Option<CoordRange>,
+    /// The command typed at the prompt
+    pub command_range: Option<CoordRange>,
+    /// The command's output. Empty until the command starts.
+    pub output_range: Option<CoordRange>,
+    pub working_directory: Option<String>,
+    pub command: Option<String>,
+    pub state: PromptState,
+    /// The command's exit status, once it has finished
+    pub exit_status: Option<i32>,
+}
+
+impl Prompt {
+    pub(crate) fn from_proto(response: &GetPromptResponse) -> Self {
+        let state = PromptState::from(response.prompt_state());
+        Self {
+            unique_id: response.unique_prompt_id().to_string(),
+            prompt_range: response.prompt_range.clone().into_option(),
+            command_range: response.command_range.clone().into_option(),
+            output_range: response.output_range.clone().into_option(),
+            working_directory: response
+                .has_working_directory()
+                .then(|| response.working_directory().to_string()),
+            command: response
+                .has_command()
+                .then(|| response.command().to_string()),
+            state,
+            exit_status: (state == PromptState::Finished && response.has_exit_status())
+                .then(|| response.exit_status() as i32),
+        }
+    }
+}
+
+impl ITerm2Connection {
+    /// Get a session's most recent prompt.
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist, or
+    /// `Error::ShellIntegrationUnavailable` if shell integration isn't installed in the session
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::prompt::PromptState;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let prompt = connection.get_prompt("active").await?;
+    /// if prompt.state == PromptState::Finished {
+    ///     println!(
+    ///         "{} exited with {:?}",
+    ///         prompt.command.unwrap_or_default(),
+    ///         prompt.exit_status
+    ///     );
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_prompt(&mut self, session_id: &str) -> Result<Prompt> {
+        let response = self.call(prompt_request(session_id, None)).await?;
+        prompt_from_response(response, session_id, None)
+    }
+
+    /// Get a prompt by its unique ID, e.g. one returned by `list_prompts()`.
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist, or
+    /// `Error::PromptNotFound` if the session has no such prompt
+    pub async fn get_prompt_by_id(
+        &mut self,
+        session_id: &str,
+        unique_prompt_id: &str,
+    ) -> Result<Prompt> {
+        let response = self
+            .call(prompt_request(session_id, Some(unique_prompt_id)))
+            .await?;
+        prompt_from_response(response, session_id, Some(unique_prompt_id))
+    }
+
+    /// List the unique IDs of a session's prompts, oldest first.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session. `"all"` is not accepted.
+    /// * `first` - Start at this prompt ID instead of the oldest prompt
+    /// * `last` - End at this prompt ID instead of the newest prompt
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    pub async fn list_prompts(
+        &mut self,
+        session_id: &str,
+        first: Option<&str>,
+        last: Option<&str>,
+    ) -> Result<Vec<String>> {
+        let mut request = ListPromptsRequest::new();
+        request.set_session(session_id.to_string());
+        if let Some(first) = first {
+            request.set_first_unique_id(first.to_string());
+        }
+        if let Some(last) = last {
+            request.set_last_unique_id(last.to_string());
+        }
+
+        let mut message = ClientOriginatedMessage::new();
+        message.set_list_prompts_request(request);
+
+        let mut response = self.call(message).await?;
+
+        if !response.has_list_prompts_response() {
+            return Err(Error::Api("Expected list prompts response".to_string()));
+        }
+        let mut list_response = response.take_list_prompts_response();
+        match list_response.status() {
+            list_prompts_response::Status::OK => {
+                Ok(std::mem::take(&mut list_response.unique_prompt_id))
+            }
+            list_prompts_response::Status::SESSION_NOT_FOUND => {
+                Err(Error::SessionNotFound(session_id.to_string()))
+            }
+        }
+    }
+}
+
+/// What happened at a prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PromptEventKind {
+    /// A new prompt was shown. `prompt` is its metadata, if iTerm2 sent it.
+    Prompt { prompt: Option<Prompt> },
+    /// A command was entered and started running
+    CommandStart { command: String },
+    /// The command finished
+    CommandEnd { exit_status: i32 },
+}
+
+/// A prompt notification, stamped when it was received.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromptEvent {
+    pub session_id: String,
+    pub prompt_id: Option<String>,
+    pub kind: PromptEventKind,
+    /// When the notification was received, for measuring durations
+    pub received: Instant,
+    /// When the notification was received, as wall-clock time
+    pub received_at: SystemTime,
+}
+
+impl PromptEvent {
+    /// Convert a prompt notification, stamping it with the current time.
+    ///
+    /// # Returns
+    /// `None` if the notification isn't a prompt notification
+    ///
+    /// # Example
+    /// ```rust
+    /// use iterm2_api::generated::api::Notification;
+    /// use iterm2_api::prompt::{PromptEvent, PromptEventKind};
+    ///
+    /// let mut notification = Notification::new();
+    /// let prompt = notification.prompt_notification.mut_or_insert_default();
+    /// prompt.set_session("SESSION-ID".to_string());
+    /// prompt.set_unique_prompt_id("PROMPT-ID".to_string());
+    /// prompt.mut_command_end().set_status(1);
+    ///
+    /// let event = PromptEvent::from_notification(&notification).unwrap();
+    /// assert_eq!(event.session_id, "SESSION-ID");
+    /// assert_eq!(event.prompt_id.as_deref(), Some("PROMPT-ID"));
+    /// assert_eq!(event.kind, PromptEventKind::CommandEnd { exit_status: 1 });
+    /// ```
+    pub fn from_notification(notification: &Notification) -> Option<Self> {
+        let prompt = notification.prompt_notification.as_ref()?;
+        let kind = match prompt.event.as_ref()? {
+            prompt_notification::Event::Prompt(shown) => PromptEventKind::Prompt {
+                prompt: shown.prompt.as_ref().map(Prompt::from_proto),
+            },
+            prompt_notification::Event::CommandStart(start) => PromptEventKind::CommandStart {
+                command: start.command().to_string(),
+            },
+            prompt_notification::Event::CommandEnd(end) => PromptEventKind::CommandEnd {
+                exit_status: end.status(),
+            },
+        };
+        Some(Self {
+            session_id: prompt.session().to_string(),
+            prompt_id: prompt
+                .has_unique_prompt_id()
+                .then(|| prompt.unique_prompt_id().to_string()),
+            kind,
+            received: Instant::now(),
+            received_at: SystemTime::now(),
+        })
+    }
+}
+
+/// Follows prompts, command starts and command ends in one session or all sessions.
+///
+/// Requires shell integration in the monitored sessions.
+#[derive(Debug, Clone)]
+pub struct PromptMonitor {
+    session_id: String,
+}
+
+impl PromptMonitor {
+    /// Subscribe to prompt notifications.
+    ///
+    /// Only prompt notifications for the monitored session are taken from the connection while
+    /// waiting; other notifications stay queued for other readers.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session to monitor, or `"all"`
+    /// * `modes` - The events to report. iTerm2 reports only new prompts if this is empty.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if iTerm2 rejects the subscription
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::generated::api::PromptMonitorMode;
+    /// # use iterm2_api::prompt::{PromptEventKind, PromptMonitor};
+    /// # use std::collections::HashMap;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let modes = [PromptMonitorMode::COMMAND_START, PromptMonitorMode::COMMAND_END];
+    /// let mut monitor = PromptMonitor::start(&mut connection, "all", &modes).await?;
+    ///
+    /// let mut started = HashMap::new();
+    /// loop {
+    ///     let event = monitor.next_event(&mut connection).await?;
+    ///     match event.kind {
+    ///         PromptEventKind::CommandStart { command } => {
+    ///             started.insert(event.session_id, (command, event.received));
+    ///         }
+    ///         PromptEventKind::CommandEnd { exit_status } => {
+    ///             if let Some((command, start)) = started.remove(&event.session_id) {
+    ///                 let elapsed = event.received - start;
+    ///                 println!("{command} exited with {exit_status} after {elapsed:?}");
+    ///             }
+    ///         }
+    ///         PromptEventKind::Prompt { .. } => {}
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub async fn start(
+        connection: &mut ITerm2Connection,
+        session_id: &str,
+        modes: &[PromptMonitorMode],
+    ) -> Result<Self> {
+        let mut request = NotificationRequest::new();
+        request.set_notification_type(NotificationType::NOTIFY_ON_PROMPT);
+        request.set_session(session_id.to_string());
+        request
+            .mut_prompt_monitor_request()
+            .modes
+            .extend(modes.iter().map(|mode| protobuf::EnumOrUnknown::new(*mode)));
+        connection.subscribe_with(request).await?;
+        Ok(Self::subscribed(session_id))
+    }
+
+    /// A monitor for a session whose prompt notifications are already subscribed to.
+    pub(crate) fn subscribed(session_id: &str) -> Self {
+        Self {
+            session_id: session_id.to_string(),
+        }
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Whether `notification` is a prompt notification for the monitored session.
+    pub fn wants(&self, notification: &Notification) -> bool {
+        notification
+            .prompt_notification
+            .as_ref()
+            .is_some_and(|prompt| self.session_id == "all" || prompt.session() == self.session_id)
+    }
+
+    /// Convert a notification if it is a prompt notification for the monitored session.
+    pub fn handle_notification(&self, notification: &Notification) -> Option<PromptEvent> {
+        let event = PromptEvent::from_notification(notification)?;
+        (self.session_id == "all" || event.session_id == self.session_id).then_some(event)
+    }
+
+    /// Wait for the next prompt event.
+    ///
+    /// # Errors
+    /// Returns `Error::Connection` if the connection is closed
+    pub async fn next_event(&self, connection: &mut ITerm2Connection) -> Result<PromptEvent> {
+        loop {
+            let notification = connection
+                .next_notification_where(|notification| self.wants(notification))
+                .await?;
+            if let Some(event) = self.handle_notification(&notification) {
+                return Ok(event);
+            }
+        }
+    }
+
+    /// Turn the monitor into a stream of events that owns the connection.
+    ///
+    /// The stream ends after the first error, unsubscribing first. Dropping the stream closes
+    /// the connection, which ends its subscriptions too.
+    pub fn into_stream(
+        self,
+        connection: ITerm2Connection,
+    ) -> impl Stream<Item = Result<PromptEvent>> {
+        futures_util::stream::unfold(Some((self, connection)), |state| async move {
+            let (monitor, mut connection) = state?;
+            match monitor.next_event(&mut connection).await {
+                Ok(event) => Some((Ok(event), Some((monitor, connection)))),
+                Err(e) => {
+                    let session_id = monitor.session_id.clone();
+                    if let Err(stop_error) = monitor.stop(&mut connection).await {
+                        tracing::warn!(
+                            "Failed to unsubscribe from prompts of {session_id}: {stop_error}"
+                        );
+                    }
+                    Some((Err(e), None))
+                }
+            }
+        })
+    }
+
+    /// Unsubscribe from prompt notifications.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if unsubscribing fails
+    pub async fn stop(self, connection: &mut ITerm2Connection) -> Result<()> {
+        connection
+            .unsubscribe(NotificationType::NOTIFY_ON_PROMPT, Some(&self.session_id))
+            .await
+    }
+}
+
+pub(crate) fn prompt_request(
+    session_id: &str,
+    unique_prompt_id: Option<&str>,
+) -> ClientOriginatedMessage {
+    let mut request = GetPromptRequest::new();
+    request.set_session(session_id.to_string());
+    if let Some(id) = unique_prompt_id {
+        request.set_unique_prompt_id(id.to_string());
+    }
+
+    let mut message = ClientOriginatedMessage::new();
+    message.set_get_prompt_request(request);
+    message
+}
+
+pub(crate) fn prompt_from_response(
+    mut response: ServerOriginatedMessage,
+    session_id: &str,
+    unique_prompt_id: Option<&str>,
+) -> Result<Prompt> {
+    if !response.has_get_prompt_response() {
+        return Err(Error::Api("Expected get prompt response".to_string()));
+    }
+    let prompt_response = response.take_get_prompt_response();
+    match prompt_response.status() {
+        get_prompt_response::Status::OK => Ok(Prompt::from_proto(&prompt_response)),
+        get_prompt_response::Status::SESSION_NOT_FOUND => {
+            Err(Error::SessionNotFound(session_id.to_string()))
+        }
+        get_prompt_response::Status::PROMPT_UNAVAILABLE => match unique_prompt_id {
+            Some(id) => Err(Error::PromptNotFound(id.to_string())),
+            None => Err(Error::ShellIntegrationUnavailable(session_id.to_string())),
+        },
+        status => Err(Error::Api(format!("Get prompt failed: {status:?}"))),
+    }
+}
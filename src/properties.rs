@@ -0,0 +1,426 @@
+//! Typed access to window and session properties.
+//!
+//! `GetPropertyRequest` and `SetPropertyRequest` identify a property by name and carry its value
+//! as JSON. The methods here wrap the documented properties with typed values.
+
+use crate::connection::ITerm2Connection;
+use crate::error::{Error, Result};
+use crate::generated::api::*;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+/// The object whose property is read or written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PropertyTarget<'a> {
+    Window(&'a str),
+    /// A session ID. Setting also accepts `"all"` and `"active"`; getting accepts `"active"`.
+    Session(&'a str),
+}
+
+impl PropertyTarget<'_> {
+    fn not_found(&self) -> Error {
+        match *self {
+            PropertyTarget::Window(id) => Error::WindowNotFound(id.to_string()),
+            PropertyTarget::Session(id) => Error::SessionNotFound(id.to_string()),
+        }
+    }
+}
+
+/// A window's position and size in screen points.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct WindowFrame {
+    pub origin: FramePoint,
+    pub size: FrameSize,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FramePoint {
+    pub x: f64,
+    pub y: f64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct FrameSize {
+    pub width: f64,
+    pub height: f64,
+}
+
+impl WindowFrame {
+    pub fn new(x: f64, y: f64, width: f64, height: f64) -> Self {
+        Self {
+            origin: FramePoint { x, y },
+            size: FrameSize { width, height },
+        }
+    }
+}
+
+impl From<&Frame> for WindowFrame {
+    fn from(frame: &Frame) -> Self {
+        Self::new(
+            f64::from(frame.origin.x()),
+            f64::from(frame.origin.y()),
+            f64::from(frame.size.width()),
+            f64::from(frame.size.height()),
+        )
+    }
+}
+
+/// A session's size in character cells.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GridSize {
+    pub width: i32,
+    pub height: i32,
+}
+
+/// Line counts of a session's buffer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineCounts {
+    /// Lines dropped from the start of history since the session began
+    pub overflow: i64,
+    /// Lines in the visible grid
+    pub grid: i64,
+    /// Lines in scrollback history, excluding the grid
+    pub history: i64,
+}
+
+impl ITerm2Connection {
+    /// Get a property as raw JSON.
+    ///
+    /// # Arguments
+    /// * `target` - The window or session to query
+    /// * `name` - The property name, e.g. `"frame"` or `"grid_size"`
+    ///
+    /// # Errors
+    /// Returns `Error::WindowNotFound` or `Error::SessionNotFound` if the target doesn't exist,
+    /// or `Error::Api` if the name isn't recognized
+    pub async fn get_property(&mut self, target: PropertyTarget<'_>, name: &str) -> Result<Value> {
+        let mut request = GetPropertyRequest::new();
+        match target {
+            PropertyTarget::Window(id) => request.set_window_id(id.to_string()),
+            PropertyTarget::Session(id) => request.set_session_id(id.to_string()),
+        }
+        request.set_name(name.to_string());
+
+        let mut message = ClientOriginatedMessage::new();
+        message.set_get_property_request(request);
+
+        let response = self.call(message).await?;
+
+        if !response.has_get_property_response() {
+            return Err(Error::Api("Expected get property response".to_string()));
+        }
+        let property_response = response.get_property_response();
+        match property_response.status() {
+            get_property_response::Status::OK => {
+                Ok(serde_json::from_str(property_response.json_value())?)
+            }
+            get_property_response::Status::INVALID_TARGET => Err(target.not_found()),
+            status => Err(Error::Api(format!(
+                "Get property {name} failed: {status:?}"
+            ))),
+        }
+    }
+
+    /// Set a property from raw JSON.
+    ///
+    /// # Errors
+    /// Returns `Error::PropertyDeferred`, `Error::PropertyImpossible` or `Error::PropertyFailed`
+    /// when iTerm2 could not apply the value, a not-found error if the target doesn't exist, or
+    /// `Error::Api` if the name or value is invalid
+    pub async fn set_property(
+        &mut self,
+        target: PropertyTarget<'_>,
+        name: &str,
+        value: &Value,
+    ) -> Result<()> {
+        let response = self.call(set_property_message(target, name, value)).await?;
+        set_property_result(&response, target, name)
+    }
+
+    /// Move and resize several windows in one batch.
+    ///
+    /// Every frame is sent before any response is read, so windows move together rather than
+    /// one at a time.
+    ///
+    /// # Errors
+    /// Returns the first failure in request order, after all requests have been answered
+    pub async fn set_window_frames(&mut self, frames: &[(&str, WindowFrame)]) -> Result<()> {
+        let mut messages = Vec::with_capacity(frames.len());
+        for (window_id, frame) in frames {
+            messages.push(set_property_message(
+                PropertyTarget::Window(window_id),
+                "frame",
+                &serde_json::to_value(frame)?,
+            ));
+        }
+
+        let responses = self.call_batch(messages).await?;
+        for ((window_id, _), response) in frames.iter().zip(responses) {
+            set_property_result(&response?, PropertyTarget::Window(window_id), "frame")?;
+        }
+        Ok(())
+    }
+
+    async fn get_typed_property<T: DeserializeOwned>(
+        &mut self,
+        target: PropertyTarget<'_>,
+        name: &str,
+    ) -> Result<T> {
+        let value = self.get_property(target, name).await?;
+        Ok(serde_json::from_value(value)?)
+    }
+
+    /// Get a window's frame.
+    ///
+    /// # Errors
+    /// Returns `Error::WindowNotFound` if the window doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let windows = connection.get_windows().await?;
+    /// let window_id = windows[0].window_id();
+    ///
+    /// let mut frame = connection.get_window_frame(window_id).await?;
+    /// frame.size.width /= 2.0;
+    /// connection.set_window_frame(window_id, &frame).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_window_frame(&mut self, window_id: &str) -> Result<WindowFrame> {
+        self.get_typed_property(PropertyTarget::Window(window_id), "frame")
+            .await
+    }
+
+    /// Move and resize a window.
+    ///
+    /// # Errors
+    /// Returns `Error::PropertyImpossible` if the window can't be resized (for example because
+    /// it is full screen), or `Error::WindowNotFound` if it doesn't exist
+    pub async fn set_window_frame(&mut self, window_id: &str, frame: &WindowFrame) -> Result<()> {
+        self.set_property(
+            PropertyTarget::Window(window_id),
+            "frame",
+            &serde_json::to_value(frame)?,
+        )
+        .await
+    }
+
+    /// Whether a window is full screen.
+    ///
+    /// # Errors
+    /// Returns `Error::WindowNotFound` if the window doesn't exist
+    pub async fn is_window_fullscreen(&mut self, window_id: &str) -> Result<bool> {
+        self.get_typed_property(PropertyTarget::Window(window_id), "fullscreen")
+            .await
+    }
+
+    /// Enter or exit full screen.
+    ///
+    /// Toggling full screen can fail transiently, for example while another window is also
+    /// toggling. If `retries` is non-zero, `Error::PropertyFailed` is retried that many times
+    /// with a growing delay. Other errors are returned immediately.
+    ///
+    /// # Errors
+    /// Returns `Error::PropertyFailed` if every attempt fails, or the first other error
+    pub async fn set_window_fullscreen(
+        &mut self,
+        window_id: &str,
+        fullscreen: bool,
+        retries: u32,
+    ) -> Result<()> {
+        let mut attempt = 0;
+        loop {
+            match self
+                .set_property(
+                    PropertyTarget::Window(window_id),
+                    "fullscreen",
+                    &Value::Bool(fullscreen),
+                )
+                .await
+            {
+                Err(Error::PropertyFailed(_)) if attempt < retries => {
+                    attempt += 1;
+                    tracing::debug!("Full screen toggle failed; retry {attempt} of {retries}");
+                    tokio::time::sleep(Duration::from_millis(250) * attempt).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Get a session's size in cells.
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    pub async fn get_grid_size(&mut self, session_id: &str) -> Result<GridSize> {
+        self.get_typed_property(PropertyTarget::Session(session_id), "grid_size")
+            .await
+    }
+
+    /// Resize a session and wait until it reports the new size.
+    ///
+    /// The window is resized to fit, so neighbouring panes may change as well. iTerm2 applies
+    /// the change asynchronously; this polls `grid_size` until it matches.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session to resize
+    /// * `size` - The requested size in cells
+    /// * `timeout` - How long to wait for the session to report the new size
+    ///
+    /// # Errors
+    /// Returns `Error::Timeout` if the size doesn't match before `timeout` elapses, or the
+    /// property errors of [`ITerm2Connection::set_property`]
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::properties::GridSize;
+    /// # use std::time::Duration;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let size = GridSize { width: 120, height: 40 };
+    /// connection
+    ///     .set_grid_size("active", size, Duration::from_secs(2))
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_grid_size(
+        &mut self,
+        session_id: &str,
+        size: GridSize,
+        timeout: Duration,
+    ) -> Result<()> {
+        let target = PropertyTarget::Session(session_id);
+        self.set_property(target, "grid_size", &serde_json::to_value(size)?)
+            .await?;
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let current: GridSize = self.get_typed_property(target, "grid_size").await?;
+            if current == size {
+                return Ok(());
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(Error::Timeout(format!(
+                    "session {session_id} is {}x{}, expected {}x{}",
+                    current.width, current.height, size.width, size.height
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    }
+
+    /// Get the line counts of a session's buffer.
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    pub async fn get_line_counts(&mut self, session_id: &str) -> Result<LineCounts> {
+        self.get_typed_property(PropertyTarget::Session(session_id), "number_of_lines")
+            .await
+    }
+
+    /// Whether a session is buried (hidden from all tabs but still running).
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    pub async fn is_session_buried(&mut self, session_id: &str) -> Result<bool> {
+        self.get_typed_property(PropertyTarget::Session(session_id), "buried")
+            .await
+    }
+
+    /// List sessions that are currently buried.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if the request fails
+    pub async fn buried_sessions(&mut self) -> Result<Vec<SessionSummary>> {
+        Ok(self.get_layout().await?.buried_sessions)
+    }
+
+    /// Bury a session, removing it from its tab without terminating it.
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// connection.bury_session("SESSION-ID").await?;
+    /// for session in connection.buried_sessions().await? {
+    ///     println!("  Buried: {}", session.title());
+    /// }
+    /// connection.unbury_session("SESSION-ID").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn bury_session(&mut self, session_id: &str) -> Result<()> {
+        self.set_buried(session_id, true).await
+    }
+
+    /// Restore a buried session into a new tab.
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    pub async fn unbury_session(&mut self, session_id: &str) -> Result<()> {
+        self.set_buried(session_id, false).await
+    }
+
+    async fn set_buried(&mut self, session_id: &str, buried: bool) -> Result<()> {
+        self.set_property(
+            PropertyTarget::Session(session_id),
+            "buried",
+            &Value::Bool(buried),
+        )
+        .await
+    }
+}
+
+fn set_property_message(
+    target: PropertyTarget<'_>,
+    name: &str,
+    value: &Value,
+) -> ClientOriginatedMessage {
+    let mut request = SetPropertyRequest::new();
+    match target {
+        PropertyTarget::Window(id) => request.set_window_id(id.to_string()),
+        PropertyTarget::Session(id) => request.set_session_id(id.to_string()),
+    }
+    request.set_name(name.to_string());
+    request.set_json_value(value.to_string());
+
+    let mut message = ClientOriginatedMessage::new();
+    message.set_set_property_request(request);
+    message
+}
+
+fn set_property_result(
+    response: &ServerOriginatedMessage,
+    target: PropertyTarget<'_>,
+    name: &str,
+) -> Result<()> {
+    if !response.has_set_property_response() {
+        return Err(Error::Api("Expected set property response".to_string()));
+    }
+    match response.set_property_response().status() {
+        set_property_response::Status::OK => Ok(()),
+        set_property_response::Status::INVALID_TARGET => Err(target.not_found()),
+        set_property_response::Status::DEFERRED => Err(Error::PropertyDeferred(name.to_string())),
+        set_property_response::Status::IMPOSSIBLE => {
+            Err(Error::PropertyImpossible(name.to_string()))
+        }
+        set_property_response::Status::FAILED => Err(Error::PropertyFailed(name.to_string())),
+        status => Err(Error::Api(format!(
+            "Set property {name} failed: {status:?}"
+        ))),
+    }
+}
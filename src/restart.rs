@@ -0,0 +1,647 @@
+//! Restarting sessions, manually or automatically.
+//!
+//! `SessionWatchdog` notices when the process of a watched session exits, through changes to the
+//! session's `jobPid` variable, and restarts the session in place. With shell integration it can
+//! instead run a command again when it finishes at the prompt. Restarts wait a growing delay
+//! between attempts and stop once a session has used its restart budget. Sessions that can't be
+//! restarted (such as tmux integration sessions) are reported and dropped rather than retried.
+
+use crate::connection::ITerm2Connection;
+use crate::error::{Error, Result};
+use crate::generated::api::*;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::time::Instant;
+
+impl ITerm2Connection {
+    /// Restart a session, running its profile's command again in the same pane.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session to restart. `"all"` is not allowed.
+    /// * `only_if_exited` - Fail instead of killing the session if its process is still running
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotRestartable` if the session can't be restarted (for example a
+    /// tmux integration session, or a running session with `only_if_exited`), or
+    /// `Error::SessionNotFound` if it doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// connection.restart_session("SESSION-ID", true).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn restart_session(&mut self, session_id: &str, only_if_exited: bool) -> Result<()> {
+        let mut request = RestartSessionRequest::new();
+        request.set_session_id(session_id.to_string());
+        request.set_only_if_exited(only_if_exited);
+
+        let mut message = ClientOriginatedMessage::new();
+        message.set_restart_session_request(request);
+
+        let response = self.call(message).await?;
+
+        if !response.has_restart_session_response() {
+            return Err(Error::Api("Expected restart session response".to_string()));
+        }
+        match response.restart_session_response().status() {
+            restart_session_response::Status::OK => Ok(()),
+            restart_session_response::Status::SESSION_NOT_FOUND => {
+                Err(Error::SessionNotFound(session_id.to_string()))
+            }
+            restart_session_response::Status::SESSION_NOT_RESTARTABLE => {
+                Err(Error::SessionNotRestartable(session_id.to_string()))
+            }
+        }
+    }
+}
+
+/// When and how often a watched session is restarted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestartPolicy {
+    /// Restarts allowed before giving up on a session
+    pub max_restarts: u32,
+    /// Delay before the first restart; doubled for each further restart
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between restarts
+    pub max_backoff: Duration,
+    /// A session that stays up this long after a restart gets its full budget back
+    pub reset_after: Duration,
+    /// Also run a command again when it finishes at a shell prompt, by typing it in once more.
+    /// This needs shell integration. The shell itself keeps running.
+    pub restart_on_command_end: bool,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        Self {
+            max_restarts: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            reset_after: Duration::from_secs(300),
+            restart_on_command_end: false,
+        }
+    }
+}
+
+impl RestartPolicy {
+    /// The delay before restart number `attempt` (starting at 1).
+    ///
+    /// # Example
+    /// ```rust
+    /// use iterm2_api::restart::RestartPolicy;
+    /// use std::time::Duration;
+    ///
+    /// let policy = RestartPolicy::default();
+    /// assert_eq!(policy.backoff(1), Duration::from_secs(1));
+    /// assert_eq!(policy.backoff(3), Duration::from_secs(4));
+    /// assert_eq!(policy.backoff(10), Duration::from_secs(60));
+    /// ```
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// What the watchdog did about a watched session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchdogEvent {
+    /// The session's process or command was started again. `attempt` counts restarts since the
+    /// budget was last reset.
+    Restarted { session_id: String, attempt: u32 },
+    /// iTerm2 reported the session as not restartable; it is no longer watched.
+    NotRestartable { session_id: String },
+    /// The session was closed; it is no longer watched.
+    Closed { session_id: String },
+    /// The session exited after using its whole restart budget; it is no longer watched.
+    GaveUp { session_id: String, restarts: u32 },
+}
+
+/// How a pending restart is carried out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Restart {
+    /// `RestartSessionRequest` for a session whose process has exited
+    Session,
+    /// Type a finished command in again
+    Command(String),
+}
+
+#[derive(Debug, Clone, Default)]
+struct Watched {
+    restarts: u32,
+    last_restart: Option<Instant>,
+    /// The command running at the session's shell prompt, if shell integration reported one
+    command: Option<String>,
+    /// When the pending restart is due, and what it does
+    pending: Option<(Instant, Restart)>,
+}
+
+/// The session variable whose changes reveal that a session's process exited.
+const JOB_PID: &str = "jobPid";
+
+/// Restarts designated sessions when their process exits.
+///
+/// A session whose process exits stays open in iTerm2 (when its profile is set not to close
+/// sessions on exit) and clears its `jobPid` variable. The watchdog follows that variable for
+/// each watched session and then restarts the session with `only_if_exited`, so a session that
+/// is still running is never killed.
+#[derive(Debug, Clone)]
+pub struct SessionWatchdog {
+    policy: RestartPolicy,
+    sessions: HashMap<String, Watched>,
+}
+
+impl SessionWatchdog {
+    /// Create a watchdog and subscribe to the notifications it needs.
+    ///
+    /// This subscribes the connection to session termination. Call `watch()` for each session
+    /// to restart and `next_event()` in a loop to drive it.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if subscribing fails
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::restart::{RestartPolicy, SessionWatchdog};
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let mut watchdog = SessionWatchdog::start(&mut connection, RestartPolicy::default()).await?;
+    /// watchdog.watch(&mut connection, "SESSION-ID").await?;
+    ///
+    /// while watchdog.watching() > 0 {
+    ///     let event = watchdog.next_event(&mut connection).await?;
+    ///     println!("{event:?}");
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn start(connection: &mut ITerm2Connection, policy: RestartPolicy) -> Result<Self> {
+        connection
+            .subscribe(NotificationType::NOTIFY_ON_TERMINATE_SESSION, None)
+            .await?;
+        Ok(Self {
+            policy,
+            sessions: HashMap::new(),
+        })
+    }
+
+    pub fn policy(&self) -> &RestartPolicy {
+        &self.policy
+    }
+
+    /// Start watching a session.
+    ///
+    /// This subscribes to changes of the session's `jobPid` variable and, if the policy restarts
+    /// on command end, to the session's command start and end notifications.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if subscribing fails
+    pub async fn watch(
+        &mut self,
+        connection: &mut ITerm2Connection,
+        session_id: &str,
+    ) -> Result<()> {
+        if self.sessions.contains_key(session_id) {
+            return Ok(());
+        }
+        connection.subscribe_with(job_request(session_id)).await?;
+        if self.policy.restart_on_command_end
+            && let Err(e) = connection.subscribe_with(command_request(session_id)).await
+        {
+            if let Err(e) = connection.unsubscribe_with(job_request(session_id)).await {
+                tracing::warn!("Failed to stop watching session {session_id}: {e}");
+            }
+            return Err(e);
+        }
+        self.sessions
+            .insert(session_id.to_string(), Watched::default());
+        Ok(())
+    }
+
+    /// Stop watching a session, cancelling any pending restart and discarding its queued
+    /// `jobPid` changes.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if unsubscribing fails
+    pub async fn unwatch(
+        &mut self,
+        connection: &mut ITerm2Connection,
+        session_id: &str,
+    ) -> Result<()> {
+        if self.sessions.remove(session_id).is_none() {
+            return Ok(());
+        }
+        let unsubscribed = connection.unsubscribe_with(job_request(session_id)).await;
+        connection.discard_notifications_where(|n| is_job_change(n, session_id));
+        if self.policy.restart_on_command_end {
+            connection
+                .unsubscribe_with(command_request(session_id))
+                .await?;
+        }
+        unsubscribed
+    }
+
+    pub fn is_watching(&self, session_id: &str) -> bool {
+        self.sessions.contains_key(session_id)
+    }
+
+    /// The number of watched sessions.
+    pub fn watching(&self) -> usize {
+        self.sessions.len()
+    }
+
+    /// Whether `notification` is one the watchdog acts on.
+    pub fn wants(&self, notification: &Notification) -> bool {
+        if let Some(terminated) = notification.terminate_session_notification.as_ref() {
+            return self.is_watching(terminated.session_id());
+        }
+        if let Some(changed) = notification.variable_changed_notification.as_ref() {
+            return changed.name() == JOB_PID && self.is_watching(changed.identifier());
+        }
+        if let Some(prompt) = notification.prompt_notification.as_ref() {
+            return self.policy.restart_on_command_end
+                && (prompt.has_command_start() || prompt.has_command_end())
+                && self.is_watching(prompt.session());
+        }
+        false
+    }
+
+    /// Track a notification, scheduling a restart if a watched session's process or command
+    /// ended.
+    ///
+    /// # Returns
+    /// `Some(WatchdogEvent::Closed)` if a watched session was closed, or
+    /// `Some(WatchdogEvent::GaveUp)` if a session that exited has no restarts left. Either way
+    /// the session is no longer watched; `next_event()` also cancels its subscriptions.
+    pub fn handle_notification(&mut self, notification: &Notification) -> Option<WatchdogEvent> {
+        if let Some(terminated) = notification.terminate_session_notification.as_ref() {
+            let session_id = terminated.session_id();
+            self.sessions.remove(session_id)?;
+            return Some(WatchdogEvent::Closed {
+                session_id: session_id.to_string(),
+            });
+        }
+        if let Some(changed) = notification.variable_changed_notification.as_ref()
+            && changed.name() == JOB_PID
+            && job_exited(changed.json_new_value())
+        {
+            return self.schedule(changed.identifier(), Restart::Session);
+        }
+        if self.policy.restart_on_command_end
+            && let Some(prompt) = notification.prompt_notification.as_ref()
+        {
+            let watched = self.sessions.get_mut(prompt.session())?;
+            if prompt.has_command_start() {
+                watched.command = Some(prompt.command_start().command().to_string());
+            } else if prompt.has_command_end()
+                && let Some(command) = watched.command.take()
+                && !command.trim().is_empty()
+            {
+                return self.schedule(prompt.session(), Restart::Command(command));
+            }
+        }
+        None
+    }
+
+    fn schedule(&mut self, session_id: &str, restart: Restart) -> Option<WatchdogEvent> {
+        let policy = &self.policy;
+        let watched = self.sessions.get_mut(session_id)?;
+        if watched.pending.is_some() {
+            return None;
+        }
+
+        let now = Instant::now();
+        if watched
+            .last_restart
+            .is_some_and(|last| now.duration_since(last) >= policy.reset_after)
+        {
+            watched.restarts = 0;
+        }
+        if watched.restarts >= policy.max_restarts {
+            let restarts = watched.restarts;
+            self.sessions.remove(session_id);
+            return Some(WatchdogEvent::GaveUp {
+                session_id: session_id.to_string(),
+                restarts,
+            });
+        }
+
+        let delay = policy.backoff(watched.restarts + 1);
+        watched.pending = Some((now + delay, restart));
+        None
+    }
+
+    /// Wait for the next restart, or for a watched session to be closed or given up on.
+    ///
+    /// Only notifications the watchdog acts on (see `wants()`) are taken from the connection;
+    /// others stay queued for other readers. If nothing is watched this waits indefinitely.
+    ///
+    /// # Errors
+    /// Returns `Error::Connection` if the connection is closed, or any error from restarting
+    /// other than the not-restartable and not-found cases, which are reported as events
+    pub async fn next_event(&mut self, connection: &mut ITerm2Connection) -> Result<WatchdogEvent> {
+        loop {
+            let due = self
+                .sessions
+                .iter()
+                .filter_map(|(id, watched)| {
+                    watched.pending.as_ref().map(|(at, _)| (*at, id.clone()))
+                })
+                .min();
+
+            let notification = match due {
+                None => {
+                    connection
+                        .next_notification_where(|n| self.wants(n))
+                        .await?
+                }
+                Some((at, session_id)) => {
+                    match tokio::time::timeout_at(
+                        at,
+                        connection.next_notification_where(|n| self.wants(n)),
+                    )
+                    .await
+                    {
+                        Ok(notification) => notification?,
+                        Err(_) => return self.restart(connection, &session_id).await,
+                    }
+                }
+            };
+            if let Some(event) = self.handle_notification(&notification) {
+                match &event {
+                    WatchdogEvent::Closed { session_id } => {
+                        self.release(connection, session_id, true).await;
+                    }
+                    WatchdogEvent::GaveUp { session_id, .. } => {
+                        self.release(connection, session_id, false).await;
+                    }
+                    _ => {}
+                }
+                return Ok(event);
+            }
+        }
+    }
+
+    async fn restart(
+        &mut self,
+        connection: &mut ITerm2Connection,
+        session_id: &str,
+    ) -> Result<WatchdogEvent> {
+        let restart = self
+            .sessions
+            .get_mut(session_id)
+            .and_then(|watched| watched.pending.take())
+            .map_or(Restart::Session, |(_, restart)| restart);
+
+        let result = match restart {
+            Restart::Session => connection.restart_session(session_id, true).await,
+            Restart::Command(command) => {
+                connection
+                    .send_text(session_id, &format!("{command}\r"))
+                    .await
+            }
+        };
+        match result {
+            Ok(()) => {
+                let watched = self.sessions.entry(session_id.to_string()).or_default();
+                watched.restarts += 1;
+                watched.last_restart = Some(Instant::now());
+                Ok(WatchdogEvent::Restarted {
+                    session_id: session_id.to_string(),
+                    attempt: watched.restarts,
+                })
+            }
+            Err(Error::SessionNotRestartable(_)) => {
+                self.release(connection, session_id, false).await;
+                Ok(WatchdogEvent::NotRestartable {
+                    session_id: session_id.to_string(),
+                })
+            }
+            Err(Error::SessionNotFound(_)) => {
+                self.release(connection, session_id, true).await;
+                Ok(WatchdogEvent::Closed {
+                    session_id: session_id.to_string(),
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Stop watching a session that was dropped, cancelling its subscriptions and discarding
+    /// its queued `jobPid` changes.
+    ///
+    /// Failures are only logged: the session is dropped either way. A closed session may no
+    /// longer have subscriptions to cancel, so failures for one are not warned about.
+    async fn release(&mut self, connection: &mut ITerm2Connection, session_id: &str, closed: bool) {
+        self.sessions.remove(session_id);
+        let mut requests = vec![job_request(session_id)];
+        if self.policy.restart_on_command_end {
+            requests.push(command_request(session_id));
+        }
+        for request in requests {
+            match connection.unsubscribe_with(request).await {
+                Ok(()) => {}
+                Err(e) if closed => {
+                    tracing::debug!("Failed to unsubscribe from closed session {session_id}: {e}");
+                }
+                Err(e) => tracing::warn!("Failed to stop watching session {session_id}: {e}"),
+            }
+        }
+        connection.discard_notifications_where(|n| is_job_change(n, session_id));
+    }
+
+    /// Unsubscribe from everything the watchdog subscribed to and stop watching all sessions.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if unsubscribing fails
+    pub async fn stop(mut self, connection: &mut ITerm2Connection) -> Result<()> {
+        let session_ids: Vec<String> = self.sessions.keys().cloned().collect();
+        for session_id in session_ids {
+            self.unwatch(connection, &session_id).await?;
+        }
+        connection
+            .unsubscribe(NotificationType::NOTIFY_ON_TERMINATE_SESSION, None)
+            .await
+    }
+}
+
+/// Subscription to changes of a session's `jobPid` variable.
+fn job_request(session_id: &str) -> NotificationRequest {
+    let mut request = NotificationRequest::new();
+    request.set_notification_type(NotificationType::NOTIFY_ON_VARIABLE_CHANGE);
+    let monitor = request.mut_variable_monitor_request();
+    monitor.set_name(JOB_PID.to_string());
+    monitor.set_scope(VariableScope::SESSION);
+    monitor.set_identifier(session_id.to_string());
+    request
+}
+
+/// Subscription to command start and end notifications in a session.
+fn command_request(session_id: &str) -> NotificationRequest {
+    let mut request = NotificationRequest::new();
+    request.set_notification_type(NotificationType::NOTIFY_ON_PROMPT);
+    request.set_session(session_id.to_string());
+    request.mut_prompt_monitor_request().modes.extend([
+        protobuf::EnumOrUnknown::new(PromptMonitorMode::COMMAND_START),
+        protobuf::EnumOrUnknown::new(PromptMonitorMode::COMMAND_END),
+    ]);
+    request
+}
+
+/// Whether `notification` is a change of a session's `jobPid` variable.
+fn is_job_change(notification: &Notification, session_id: &str) -> bool {
+    notification
+        .variable_changed_notification
+        .as_ref()
+        .is_some_and(|changed| changed.name() == JOB_PID && changed.identifier() == session_id)
+}
+
+/// Whether a new `jobPid` value means the session has no process left.
+fn job_exited(json_value: &str) -> bool {
+    match serde_json::from_str::<serde_json::Value>(json_value) {
+        Ok(serde_json::Value::Null) => true,
+        Ok(value) => value.as_i64().is_some_and(|pid| pid <= 0),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watched(restart_on_command_end: bool) -> SessionWatchdog {
+        let mut watchdog = SessionWatchdog {
+            policy: RestartPolicy {
+                max_restarts: 1,
+                restart_on_command_end,
+                ..RestartPolicy::default()
+            },
+            sessions: HashMap::new(),
+        };
+        watchdog
+            .sessions
+            .insert("s1".to_string(), Watched::default());
+        watchdog
+    }
+
+    fn job_pid(session_id: &str, value: &str) -> Notification {
+        let mut notification = Notification::new();
+        let changed = notification
+            .variable_changed_notification
+            .mut_or_insert_default();
+        changed.set_scope(VariableScope::SESSION);
+        changed.set_identifier(session_id.to_string());
+        changed.set_name(JOB_PID.to_string());
+        changed.set_json_new_value(value.to_string());
+        notification
+    }
+
+    fn command_start(session_id: &str, command: &str) -> Notification {
+        let mut notification = Notification::new();
+        let prompt = notification.prompt_notification.mut_or_insert_default();
+        prompt.set_session(session_id.to_string());
+        prompt.mut_command_start().set_command(command.to_string());
+        notification
+    }
+
+    fn command_end(session_id: &str) -> Notification {
+        let mut notification = Notification::new();
+        let prompt = notification.prompt_notification.mut_or_insert_default();
+        prompt.set_session(session_id.to_string());
+        prompt.mut_command_end().set_status(255);
+        notification
+    }
+
+    fn pending(watchdog: &SessionWatchdog) -> Option<Restart> {
+        watchdog.sessions["s1"]
+            .pending
+            .as_ref()
+            .map(|(_, restart)| restart.clone())
+    }
+
+    #[test]
+    fn running_jobs_are_left_alone() {
+        let mut watchdog = watched(false);
+        assert!(watchdog.wants(&job_pid("s1", "4242")));
+        assert_eq!(watchdog.handle_notification(&job_pid("s1", "4242")), None);
+        assert_eq!(pending(&watchdog), None);
+    }
+
+    #[test]
+    fn exited_jobs_are_restarted_then_given_up_on() {
+        let mut watchdog = watched(false);
+        assert_eq!(watchdog.handle_notification(&job_pid("s1", "null")), None);
+        assert_eq!(pending(&watchdog), Some(Restart::Session));
+
+        let watched = watchdog.sessions.get_mut("s1").unwrap();
+        watched.pending = None;
+        watched.restarts = 1;
+        watched.last_restart = Some(Instant::now());
+        assert_eq!(
+            watchdog.handle_notification(&job_pid("s1", "0")),
+            Some(WatchdogEvent::GaveUp {
+                session_id: "s1".to_string(),
+                restarts: 1
+            })
+        );
+        assert!(!watchdog.is_watching("s1"));
+    }
+
+    #[test]
+    fn terminated_sessions_are_closed() {
+        let mut watchdog = watched(false);
+        let mut notification = Notification::new();
+        notification
+            .terminate_session_notification
+            .mut_or_insert_default()
+            .set_session_id("s1".to_string());
+        assert_eq!(
+            watchdog.handle_notification(&notification),
+            Some(WatchdogEvent::Closed {
+                session_id: "s1".to_string()
+            })
+        );
+        assert_eq!(watchdog.watching(), 0);
+    }
+
+    #[test]
+    fn finished_commands_are_run_again() {
+        let mut watchdog = watched(true);
+        assert!(!watchdog.wants(&command_end("s2")));
+        watchdog.handle_notification(&command_start("s1", "ssh logs tail -f app.log"));
+        assert_eq!(watchdog.handle_notification(&command_end("s1")), None);
+        assert_eq!(
+            pending(&watchdog),
+            Some(Restart::Command("ssh logs tail -f app.log".to_string()))
+        );
+
+        let request = command_request("s1");
+        assert_eq!(request.session(), "s1");
+        assert_eq!(request.prompt_monitor_request().modes.len(), 2);
+
+        let mut watchdog = watched(false);
+        assert!(!watchdog.wants(&command_end("s1")));
+        watchdog.handle_notification(&command_start("s1", "make"));
+        watchdog.handle_notification(&command_end("s1"));
+        assert_eq!(pending(&watchdog), None);
+    }
+
+    #[test]
+    fn job_changes_are_matched_by_session() {
+        assert!(is_job_change(&job_pid("s1", "null"), "s1"));
+        assert!(!is_job_change(&job_pid("s2", "null"), "s1"));
+        assert!(!is_job_change(&command_end("s1"), "s1"));
+        assert_eq!(
+            job_request("s1").variable_monitor_request().identifier(),
+            "s1"
+        );
+    }
+}
@@ -0,0 +1,443 @@
+//! Searching session contents with regular expressions.
+//!
+//! Lines are read in pages, soft-wrapped lines are joined so matches can span a wrap, and every
+//! match is reported as a `CoordRange` in iTerm2's stable line numbering, ready to be used in a
+//! `SelectionRequest`.
+
+use crate::buffer::{BufferLine, BufferRange, buffer_from_response, buffer_request, coord};
+use crate::cells::CellGrid;
+use crate::connection::ITerm2Connection;
+use crate::error::{Error, Result};
+use crate::generated::api::CoordRange;
+use crate::tree;
+use regex::Regex;
+
+/// Lines requested per `GetBufferRequest` while searching history.
+const PAGE_LINES: i64 = 1000;
+
+/// Which part of a session to search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchScope {
+    /// The lines currently on screen
+    Screen,
+    /// The last `n` lines, including the screen
+    Trailing(i32),
+    /// Lines `first..end` by stable line number
+    Lines(i64, i64),
+    /// The whole scrollback history and the screen
+    All,
+}
+
+/// A regex match in a session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchMatch {
+    /// The matched text
+    pub text: String,
+    /// The matched cells. `end` is the first cell after the match.
+    pub range: CoordRange,
+    /// The whole logical line the match was found in
+    pub line: String,
+}
+
+/// Matches found in one session by `search_all_sessions()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionMatches {
+    pub session_id: String,
+    pub title: String,
+    pub tab_id: String,
+    pub window_id: String,
+    pub matches: Vec<SearchMatch>,
+}
+
+/// Where each character of a logical line's text is on screen.
+struct CharPosition {
+    byte: usize,
+    y: i64,
+    column: usize,
+    /// Cells the character covers
+    width: usize,
+}
+
+fn char_positions(rows: &[BufferLine]) -> (String, Vec<CharPosition>) {
+    let mut text = String::new();
+    let mut positions = Vec::new();
+    for row in rows {
+        let grid = CellGrid::from_line(row);
+        for (index, c) in row.text.chars().enumerate() {
+            // Code points the grid doesn't account for sit after the last cell
+            let column = grid.column_of(index).unwrap_or(grid.width());
+            let width = if grid.is_wide_continuation(column + 1) {
+                2
+            } else {
+                1
+            };
+            positions.push(CharPosition {
+                byte: text.len(),
+                y: row.y,
+                column,
+                width,
+            });
+            text.push(c);
+        }
+    }
+    (text, positions)
+}
+
+/// Find regex matches in screen lines, joining soft-wrapped lines first.
+///
+/// Empty matches are skipped.
+///
+/// # Example
+/// ```rust
+/// use iterm2_api::buffer::BufferLine;
+/// use iterm2_api::generated::api::CodePointsPerCell;
+/// use iterm2_api::search::find_matches;
+/// use regex::Regex;
+///
+/// let line = |y, text: &str, soft_wrapped| {
+///     let mut cells = CodePointsPerCell::new();
+///     cells.set_repeats(text.chars().count() as i32);
+///     BufferLine {
+///         y,
+///         text: text.to_string(),
+///         soft_wrapped,
+///         code_points_per_cell: vec![cells],
+///         styles: Vec::new(),
+///     }
+/// };
+/// // "error: disk full" wrapped after "error: di"
+/// let lines = [line(40, "error: di", true), line(41, "sk full", false)];
+///
+/// let matches = find_matches(&lines, &Regex::new(r"disk \w+").unwrap());
+/// assert_eq!(matches[0].text, "disk full");
+/// let range = &matches[0].range;
+/// assert_eq!((range.start.x(), range.start.y()), (7, 40));
+/// assert_eq!((range.end.x(), range.end.y()), (7, 41));
+/// ```
+pub fn find_matches(lines: &[BufferLine], regex: &Regex) -> Vec<SearchMatch> {
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        let end = lines[start..]
+            .iter()
+            .position(|line| !line.soft_wrapped)
+            .map_or(lines.len(), |offset| start + offset + 1);
+        let (text, positions) = char_positions(&lines[start..end]);
+        start = end;
+
+        for found in regex.find_iter(&text) {
+            if found.is_empty() {
+                continue;
+            }
+            let first = positions.partition_point(|p| p.byte < found.start());
+            let last = positions.partition_point(|p| p.byte < found.end()) - 1;
+            let (first, last) = (&positions[first], &positions[last]);
+
+            let mut range = CoordRange::new();
+            range.start = protobuf::MessageField::some(coord(first.column, first.y));
+            range.end = protobuf::MessageField::some(coord(last.column + last.width, last.y));
+            matches.push(SearchMatch {
+                text: found.as_str().to_string(),
+                range,
+                line: text.clone(),
+            });
+        }
+    }
+    matches
+}
+
+/// Matches collected from consecutive pages of lines.
+struct PagedMatches<'a> {
+    regex: &'a Regex,
+    /// Lines of a logical line that continues into the next page
+    carried: Vec<BufferLine>,
+    matches: Vec<SearchMatch>,
+}
+
+impl<'a> PagedMatches<'a> {
+    fn new(regex: &'a Regex) -> Self {
+        Self {
+            regex,
+            carried: Vec::new(),
+            matches: Vec::new(),
+        }
+    }
+
+    /// Search the next page. If `more` pages follow, a trailing soft-wrapped logical line is
+    /// kept back to be searched with the rest of its lines.
+    fn push(&mut self, page: Vec<BufferLine>, more: bool) {
+        let mut lines = std::mem::take(&mut self.carried);
+        lines.extend(page);
+        if more {
+            let complete = lines
+                .iter()
+                .rposition(|line| !line.soft_wrapped)
+                .map_or(0, |index| index + 1);
+            self.carried = lines.split_off(complete);
+        }
+        self.matches.extend(find_matches(&lines, self.regex));
+    }
+
+    fn finish(mut self) -> Vec<SearchMatch> {
+        self.matches.extend(find_matches(&self.carried, self.regex));
+        self.matches
+    }
+}
+
+impl ITerm2Connection {
+    /// Search a session's screen and scrollback history.
+    ///
+    /// History is read in pages so that large scrollback buffers don't have to be fetched in
+    /// one response.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session to search
+    /// * `regex` - The pattern to look for
+    /// * `scope` - Which lines to search
+    ///
+    /// # Returns
+    /// Matches in the order they appear, oldest first
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::search::SearchScope;
+    /// # use regex::Regex;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let regex = Regex::new(r"(?i)error: .*")?;
+    /// for found in connection.search("active", &regex, SearchScope::All).await? {
+    ///     println!("line {}: {}", found.range.start.y(), found.text);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn search(
+        &mut self,
+        session_id: &str,
+        regex: &Regex,
+        scope: SearchScope,
+    ) -> Result<Vec<SearchMatch>> {
+        let (first, end) = match scope {
+            SearchScope::Screen => {
+                let buffer = self
+                    .get_buffer(session_id, BufferRange::Screen, false)
+                    .await?;
+                return Ok(find_matches(&buffer.lines, regex));
+            }
+            SearchScope::Lines(first, end) => (first, end),
+            SearchScope::Trailing(_) | SearchScope::All => {
+                let counts = self.get_line_counts(session_id).await?;
+                let end = counts.overflow + counts.history + counts.grid;
+                let first = match scope {
+                    SearchScope::Trailing(n) => end - i64::from(n),
+                    _ => counts.overflow,
+                };
+                (first.max(counts.overflow), end)
+            }
+        };
+
+        let mut pages = PagedMatches::new(regex);
+        let mut page_start = first;
+        while page_start < end {
+            let page_end = (page_start + PAGE_LINES).min(end);
+            let buffer = self
+                .get_buffer(session_id, BufferRange::lines(page_start, page_end), false)
+                .await?;
+            if buffer.lines.is_empty() {
+                break;
+            }
+            page_start += buffer.lines.len() as i64;
+            pages.push(buffer.lines, page_start < end);
+        }
+        Ok(pages.finish())
+    }
+
+    /// Search every open session at once.
+    ///
+    /// All buffer requests are sent before any response is read, so iTerm2 serves them
+    /// concurrently instead of one pane at a time. Sessions that close during the search are
+    /// skipped.
+    ///
+    /// # Arguments
+    /// * `regex` - The pattern to look for
+    /// * `history_lines` - Lines of scrollback to search above each screen, 0 for the screen
+    ///   only, or `i32::MAX` for all history
+    ///
+    /// # Returns
+    /// Sessions with at least one match, in layout order
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if a request fails for a reason other than the session closing
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use regex::Regex;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let regex = Regex::new(r"panicked at|Traceback")?;
+    /// for session in connection.search_all_sessions(&regex, 500).await? {
+    ///     println!("{} (tab {}):", session.title, session.tab_id);
+    ///     for found in &session.matches {
+    ///         println!("  {}: {}", found.range.start.y(), found.line);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn search_all_sessions(
+        &mut self,
+        regex: &Regex,
+        history_lines: i32,
+    ) -> Result<Vec<SessionMatches>> {
+        let layout = self.get_layout().await?;
+        let mut targets = Vec::new();
+        let mut messages = Vec::new();
+        for window in &layout.windows {
+            for tab in &window.tabs {
+                for session in tree::tab_sessions(tab) {
+                    let range = search_range(session.grid_size.height(), history_lines);
+                    messages.push(buffer_request(session.unique_identifier(), &range, false));
+                    targets.push(SessionMatches {
+                        session_id: session.unique_identifier().to_string(),
+                        title: session.title().to_string(),
+                        tab_id: tab.tab_id().to_string(),
+                        window_id: window.window_id().to_string(),
+                        matches: Vec::new(),
+                    });
+                }
+            }
+        }
+
+        let responses = self.call_batch(messages).await?;
+        let mut results = Vec::new();
+        for (mut target, response) in targets.into_iter().zip(responses) {
+            let buffer = match response.and_then(|r| buffer_from_response(r, &target.session_id)) {
+                Ok(buffer) => buffer,
+                Err(Error::SessionNotFound(_)) => continue,
+                Err(e) => return Err(e),
+            };
+            target.matches = find_matches(&buffer.lines, regex);
+            if !target.matches.is_empty() {
+                results.push(target);
+            }
+        }
+        Ok(results)
+    }
+}
+
+/// The lines to search in a session whose screen is `height` lines tall.
+fn search_range(height: i32, history_lines: i32) -> BufferRange {
+    if history_lines > 0 {
+        BufferRange::Trailing(height.saturating_add(history_lines))
+    } else {
+        BufferRange::Screen
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generated::api::CodePointsPerCell;
+
+    /// A line with one code point per cell, except that `日` takes two cells.
+    fn line(y: i64, text: &str, soft_wrapped: bool) -> BufferLine {
+        let mut code_points_per_cell = Vec::new();
+        for c in text.chars() {
+            let mut run = CodePointsPerCell::new();
+            run.set_num_code_points(1);
+            run.set_repeats(1);
+            code_points_per_cell.push(run);
+            if c == '日' {
+                let mut empty = CodePointsPerCell::new();
+                empty.set_num_code_points(0);
+                empty.set_repeats(1);
+                code_points_per_cell.push(empty);
+            }
+        }
+        BufferLine {
+            y,
+            text: text.to_string(),
+            soft_wrapped,
+            code_points_per_cell,
+            styles: Vec::new(),
+        }
+    }
+
+    fn span(found: &SearchMatch) -> ((i32, i64), (i32, i64)) {
+        let range = &found.range;
+        (
+            (range.start.x(), range.start.y()),
+            (range.end.x(), range.end.y()),
+        )
+    }
+
+    #[test]
+    fn matches_use_cell_columns() {
+        let lines = [line(7, "日本 err 1", false), line(8, "err 2", false)];
+        let matches = find_matches(&lines, &Regex::new(r"err \d").unwrap());
+        assert_eq!(matches.len(), 2);
+        // "日" is two cells wide, so "err" starts at column 4
+        assert_eq!(span(&matches[0]), ((4, 7), (9, 7)));
+        assert_eq!(matches[0].line, "日本 err 1");
+        assert_eq!(span(&matches[1]), ((0, 8), (5, 8)));
+    }
+
+    #[test]
+    fn wide_characters_end_after_both_cells() {
+        let lines = [line(0, "a日", false)];
+        let matches = find_matches(&lines, &Regex::new("日").unwrap());
+        assert_eq!(span(&matches[0]), ((1, 0), (3, 0)));
+    }
+
+    #[test]
+    fn empty_matches_are_skipped() {
+        let lines = [line(0, "abc", false)];
+        assert!(find_matches(&lines, &Regex::new("x*").unwrap()).is_empty());
+        assert!(find_matches(&[], &Regex::new("a").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn matches_span_pages() {
+        let regex = Regex::new("needle").unwrap();
+        let mut pages = PagedMatches::new(&regex);
+        pages.push(vec![line(0, "x", false), line(1, "hay ne", true)], true);
+        pages.push(vec![line(2, "ed", true)], true);
+        pages.push(
+            vec![line(3, "le hay", false), line(4, "needle", false)],
+            false,
+        );
+
+        let matches = pages.finish();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(span(&matches[0]), ((4, 1), (2, 3)));
+        assert_eq!(matches[0].line, "hay needle hay");
+        assert_eq!(span(&matches[1]), ((0, 4), (6, 4)));
+    }
+
+    #[test]
+    fn carried_lines_are_searched_at_the_end() {
+        let regex = Regex::new("tail").unwrap();
+        let mut pages = PagedMatches::new(&regex);
+        // The range ended while the last line was still wrapping
+        pages.push(vec![line(10, "ta", true)], true);
+        pages.push(vec![line(11, "il", true)], true);
+        let matches = pages.finish();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(span(&matches[0]), ((0, 10), (2, 11)));
+    }
+
+    #[test]
+    fn search_range_covers_screen_and_history() {
+        assert_eq!(search_range(40, 0), BufferRange::Screen);
+        assert_eq!(search_range(40, -5), BufferRange::Screen);
+        assert_eq!(search_range(40, 500), BufferRange::Trailing(540));
+        assert_eq!(search_range(40, i32::MAX), BufferRange::Trailing(i32::MAX));
+    }
+}
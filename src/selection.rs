@@ -0,0 +1,392 @@
+//! Reading and changing a session's selection.
+//!
+//! A selection is made of sub-selections, each a range of cells with a selection mode. Ranges
+//! use stable line numbers, so `CoordRange`s from `search()` or `get_hyperlinks()` can be
+//! selected directly.
+
+use crate::buffer::{BufferLine, BufferRange};
+use crate::connection::ITerm2Connection;
+use crate::error::{Error, Result};
+use crate::generated::api;
+use crate::generated::api::*;
+use crate::links::BufferHyperlink;
+use crate::search::SearchMatch;
+
+/// A contiguous part of a selection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubSelection {
+    /// The selected cells. `end` is the first cell after the selection.
+    pub range: CoordRange,
+    /// Limit the selection to columns `start..end`, for selections inside a vertical split
+    /// of an application such as tmux or vim
+    pub columns: Option<(i64, i64)>,
+    pub mode: SelectionMode,
+    /// Whether this part continues into the next without a line break
+    pub connected: bool,
+}
+
+impl SubSelection {
+    /// A character-mode selection of `range`.
+    pub fn new(range: CoordRange) -> Self {
+        Self::with_mode(range, SelectionMode::CHARACTER)
+    }
+
+    pub fn with_mode(range: CoordRange, mode: SelectionMode) -> Self {
+        Self {
+            range,
+            columns: None,
+            mode,
+            connected: false,
+        }
+    }
+
+    fn from_proto(sub: &api::SubSelection) -> Self {
+        let windowed = &sub.windowed_coord_range;
+        let columns = windowed
+            .columns
+            .as_ref()
+            .map(|columns| (columns.location(), columns.location() + columns.length()));
+        Self {
+            range: windowed.coord_range.clone().unwrap_or_default(),
+            columns,
+            mode: sub.selection_mode(),
+            connected: sub.connected(),
+        }
+    }
+
+    fn to_proto(&self) -> api::SubSelection {
+        let mut windowed = WindowedCoordRange::new();
+        windowed.coord_range = protobuf::MessageField::some(self.range.clone());
+        if let Some((start, end)) = self.columns {
+            let mut columns = Range::new();
+            columns.set_location(start);
+            columns.set_length(end - start);
+            windowed.columns = protobuf::MessageField::some(columns);
+        }
+
+        let mut sub = api::SubSelection::new();
+        sub.windowed_coord_range = protobuf::MessageField::some(windowed);
+        sub.set_selection_mode(self.mode);
+        sub.set_connected(self.connected);
+        sub
+    }
+
+    /// The first and last line holding selected cells.
+    ///
+    /// A range that ends at column 0 selects nothing on its end line, except in box mode where
+    /// the end only sets the right edge.
+    fn line_span(&self) -> (i64, i64) {
+        let (start, end) = (&self.range.start, &self.range.end);
+        if end.x() == 0 && end.y() > start.y() && self.mode != SelectionMode::BOX {
+            (start.y(), end.y() - 1)
+        } else {
+            (start.y(), end.y())
+        }
+    }
+
+    /// The selected text of `lines`, which must include every line of `line_span()`.
+    ///
+    /// Lines are separated by line breaks except where a soft-wrapped line continues. Trailing
+    /// blanks are removed from lines that end in a line break, but kept where a wrapped line
+    /// continues so that words split by the wrap stay apart.
+    fn text(&self, lines: &[BufferLine]) -> String {
+        let (first, last) = self.line_span();
+        let to_column = |x: i64| usize::try_from(x).unwrap_or(0);
+        let (low, high) = self.columns.map_or((0, usize::MAX), |(low, high)| {
+            (to_column(low), to_column(high))
+        });
+        let start_x = to_column(self.range.start.x().into());
+        let end_x = if last < self.range.end.y() {
+            high
+        } else {
+            to_column(self.range.end.x().into())
+        };
+
+        let mut text = String::new();
+        for line in lines.iter().filter(|line| (first..=last).contains(&line.y)) {
+            let grid = line.cells();
+            let (from, to) = if self.mode == SelectionMode::BOX {
+                (start_x, end_x)
+            } else {
+                let from = if line.y == first { start_x } else { low };
+                let to = if line.y == last { end_x } else { high };
+                (from.max(low), to.min(high))
+            };
+            let to = to.min(grid.width());
+            // A wrapped line continues on the next row unless the selection is clipped to a box
+            // or to columns
+            let joined =
+                line.soft_wrapped && self.mode != SelectionMode::BOX && self.columns.is_none();
+            if from < to {
+                let selected = grid.text_in(from..to);
+                text.push_str(if joined {
+                    &selected
+                } else {
+                    selected.trim_end()
+                });
+            }
+            if line.y < last && !joined {
+                text.push('\n');
+            }
+        }
+        text
+    }
+}
+
+impl From<&SearchMatch> for SubSelection {
+    fn from(found: &SearchMatch) -> Self {
+        Self::new(found.range.clone())
+    }
+}
+
+impl From<&BufferHyperlink> for SubSelection {
+    fn from(link: &BufferHyperlink) -> Self {
+        Self::new(link.range.clone())
+    }
+}
+
+/// A session's selection. An empty selection means nothing is selected.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    pub sub_selections: Vec<SubSelection>,
+}
+
+impl Selection {
+    pub fn new(sub_selections: Vec<SubSelection>) -> Self {
+        Self { sub_selections }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sub_selections.is_empty()
+    }
+}
+
+impl FromIterator<SubSelection> for Selection {
+    fn from_iter<I: IntoIterator<Item = SubSelection>>(iter: I) -> Self {
+        Self::new(iter.into_iter().collect())
+    }
+}
+
+impl ITerm2Connection {
+    /// Get a session's current selection.
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    pub async fn get_selection(&mut self, session_id: &str) -> Result<Selection> {
+        let mut get = selection_request::GetSelectionRequest::new();
+        get.set_session_id(session_id.to_string());
+        let mut request = SelectionRequest::new();
+        request.set_get_selection_request(get);
+
+        let mut response = self.selection_request(session_id, request).await?;
+        let selection = response
+            .take_get_selection_response()
+            .selection
+            .take()
+            .unwrap_or_default();
+        Ok(selection
+            .sub_selections
+            .iter()
+            .map(SubSelection::from_proto)
+            .collect())
+    }
+
+    /// Replace a session's selection. Pass an empty selection to deselect.
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist, or `Error::Api` if a range
+    /// is outside the buffer
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::search::SearchScope;
+    /// # use iterm2_api::selection::{Selection, SubSelection};
+    /// # use regex::Regex;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let regex = Regex::new(r"FAILED \S+")?;
+    /// let matches = connection.search("active", &regex, SearchScope::Screen).await?;
+    ///
+    /// let selection: Selection = matches.iter().map(SubSelection::from).collect();
+    /// connection.set_selection("active", &selection).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn set_selection(&mut self, session_id: &str, selection: &Selection) -> Result<()> {
+        let mut proto = api::Selection::new();
+        proto.sub_selections = selection
+            .sub_selections
+            .iter()
+            .map(SubSelection::to_proto)
+            .collect();
+        let mut set = selection_request::SetSelectionRequest::new();
+        set.set_session_id(session_id.to_string());
+        set.selection = protobuf::MessageField::some(proto);
+        let mut request = SelectionRequest::new();
+        request.set_set_selection_request(set);
+
+        self.selection_request(session_id, request).await?;
+        Ok(())
+    }
+
+    /// Get the text of a session's current selection.
+    ///
+    /// Parts are joined with line breaks unless they are connected. Box selections are read
+    /// column by column, one line of text per row. Trailing blanks are removed from lines that
+    /// end in a line break.
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let text = connection.selection_text("active").await?;
+    /// println!("Selected: {text}");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn selection_text(&mut self, session_id: &str) -> Result<String> {
+        let selection = self.get_selection(session_id).await?;
+        let mut text = String::new();
+        for (i, sub) in selection.sub_selections.iter().enumerate() {
+            if i > 0 && !selection.sub_selections[i - 1].connected {
+                text.push('\n');
+            }
+            text.push_str(&self.sub_selection_text(session_id, sub).await?);
+        }
+        Ok(text)
+    }
+
+    pub(crate) async fn sub_selection_text(
+        &mut self,
+        session_id: &str,
+        sub: &SubSelection,
+    ) -> Result<String> {
+        let (first, last) = sub.line_span();
+        let buffer = self
+            .get_buffer(session_id, BufferRange::lines(first, last + 1), false)
+            .await?;
+        Ok(sub.text(&buffer.lines))
+    }
+
+    async fn selection_request(
+        &mut self,
+        session_id: &str,
+        request: SelectionRequest,
+    ) -> Result<api::SelectionResponse> {
+        let mut message = ClientOriginatedMessage::new();
+        message.set_selection_request(request);
+
+        let mut response = self.call(message).await?;
+
+        if !response.has_selection_response() {
+            return Err(Error::Api("Expected selection response".to_string()));
+        }
+        let selection_response = response.take_selection_response();
+        match selection_response.status() {
+            selection_response::Status::OK => Ok(selection_response),
+            selection_response::Status::INVALID_SESSION => {
+                Err(Error::SessionNotFound(session_id.to_string()))
+            }
+            status => Err(Error::Api(format!("Selection request failed: {status:?}"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(y: i64, text: &str, soft_wrapped: bool) -> BufferLine {
+        let mut cells = CodePointsPerCell::new();
+        cells.set_num_code_points(1);
+        cells.set_repeats(text.chars().count() as i32);
+        BufferLine {
+            y,
+            text: text.to_string(),
+            soft_wrapped,
+            code_points_per_cell: vec![cells],
+            styles: Vec::new(),
+        }
+    }
+
+    fn range((start_x, start_y): (i32, i64), (end_x, end_y): (i32, i64)) -> CoordRange {
+        let mut range = CoordRange::new();
+        let start = range.start.mut_or_insert_default();
+        start.set_x(start_x);
+        start.set_y(start_y);
+        let end = range.end.mut_or_insert_default();
+        end.set_x(end_x);
+        end.set_y(end_y);
+        range
+    }
+
+    #[test]
+    fn wrapped_lines_keep_their_spaces() {
+        // "foo bar" wrapped after "foo "
+        let lines = [line(0, "foo ", true), line(1, "bar  ", false)];
+        let sub = SubSelection::new(range((0, 0), (5, 1)));
+        assert_eq!(sub.text(&lines), "foo bar");
+    }
+
+    #[test]
+    fn hard_lines_are_trimmed_and_broken() {
+        let lines = [
+            line(3, "one   ", false),
+            line(4, "two   ", false),
+            line(5, "three", false),
+        ];
+        let sub = SubSelection::new(range((1, 3), (3, 5)));
+        assert_eq!(sub.text(&lines), "ne\ntwo\nthr");
+    }
+
+    #[test]
+    fn an_end_at_column_zero_stops_at_the_previous_line() {
+        let lines = [line(0, "first  ", false), line(1, "second", false)];
+        let sub = SubSelection::new(range((0, 0), (0, 1)));
+        assert_eq!(sub.line_span(), (0, 0));
+        assert_eq!(sub.text(&lines), "first");
+
+        // Still the whole previous line when it wraps into the end line
+        let lines = [line(0, "ab ", true), line(1, "cd", false)];
+        assert_eq!(sub.text(&lines), "ab ");
+
+        // An empty range on one line stays empty
+        let sub = SubSelection::new(range((0, 1), (0, 1)));
+        assert_eq!(sub.line_span(), (1, 1));
+        assert_eq!(sub.text(&lines), "");
+    }
+
+    #[test]
+    fn box_selections_read_columns_per_row() {
+        let lines = [line(0, "abcdef", true), line(1, "ghijkl", false)];
+        let sub = SubSelection::with_mode(range((1, 0), (3, 1)), SelectionMode::BOX);
+        assert_eq!(sub.text(&lines), "bc\nhi");
+    }
+
+    #[test]
+    fn column_limits_clip_every_row() {
+        let lines = [line(0, "abcdef", true), line(1, "gh  kl", false)];
+        let mut sub = SubSelection::new(range((0, 0), (6, 1)));
+        sub.columns = Some((2, 4));
+        assert_eq!(sub.text(&lines), "cd\n");
+        sub.columns = Some((0, 3));
+        assert_eq!(sub.text(&lines), "abc\ngh");
+    }
+
+    #[test]
+    fn proto_round_trip() {
+        let mut sub = SubSelection::with_mode(range((1, 2), (3, 4)), SelectionMode::WHOLE_LINE);
+        sub.columns = Some((5, 9));
+        sub.connected = true;
+        let proto = sub.to_proto();
+        assert_eq!(proto.windowed_coord_range.columns.length(), 4);
+        assert_eq!(SubSelection::from_proto(&proto), sub);
+    }
+}
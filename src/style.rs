@@ -0,0 +1,476 @@
+//! Decoding cell styles.
+//!
+//! With `include_styles`, each buffer line carries a run-length encoded list of `CellStyle`s
+//! whose colors may be palette indices, "alternate" colors such as the default foreground, or
+//! RGB. `CellAttributes` is the decoded form of one cell's style, with every color resolved to
+//! RGB against a `Palette` read from the session's profile.
+
+use crate::buffer::BufferLine;
+use crate::connection::ITerm2Connection;
+use crate::error::Result;
+use crate::generated::api::cell_style::{BgColor, FgColor};
+use crate::generated::api::*;
+use serde_json::{Map, Value};
+
+/// A 24-bit color.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Rgb {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl Rgb {
+    pub const fn new(red: u8, green: u8, blue: u8) -> Self {
+        Self { red, green, blue }
+    }
+
+    /// The color as `#rrggbb`.
+    pub fn hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+
+    /// Parse a profile color such as `{"Red Component": 0.5, ...}`.
+    ///
+    /// Components are fractions from 0 to 1.
+    pub fn from_profile_color(value: &Value) -> Option<Self> {
+        let component = |name: &str| {
+            let fraction = value.get(name)?.as_f64()?;
+            Some((fraction.clamp(0.0, 1.0) * 255.0).round() as u8)
+        };
+        Some(Self::new(
+            component("Red Component")?,
+            component("Green Component")?,
+            component("Blue Component")?,
+        ))
+    }
+
+    /// The color as a profile color value, for setting color profile properties.
+    pub fn to_profile_color(&self) -> Value {
+        let component = |channel: u8| f64::from(channel) / 255.0;
+        serde_json::json!({
+            "Red Component": component(self.red),
+            "Green Component": component(self.green),
+            "Blue Component": component(self.blue),
+            "Alpha Component": 1.0,
+            "Color Space": "sRGB",
+        })
+    }
+}
+
+impl From<&RGBColor> for Rgb {
+    fn from(color: &RGBColor) -> Self {
+        let channel = |value: u32| value.min(255) as u8;
+        Self::new(
+            channel(color.red()),
+            channel(color.green()),
+            channel(color.blue()),
+        )
+    }
+}
+
+/// The colors needed to resolve cell styles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    /// ANSI colors 0-15
+    pub ansi: [Rgb; 16],
+    pub foreground: Rgb,
+    pub background: Rgb,
+}
+
+impl Default for Palette {
+    /// iTerm2's default colors.
+    fn default() -> Self {
+        Self {
+            ansi: [
+                Rgb::new(0x00, 0x00, 0x00),
+                Rgb::new(0xc9, 0x1b, 0x00),
+                Rgb::new(0x00, 0xc2, 0x00),
+                Rgb::new(0xc7, 0xc4, 0x00),
+                Rgb::new(0x02, 0x25, 0xc7),
+                Rgb::new(0xc9, 0x30, 0xc7),
+                Rgb::new(0x00, 0xc5, 0xc7),
+                Rgb::new(0xc7, 0xc7, 0xc7),
+                Rgb::new(0x67, 0x67, 0x67),
+                Rgb::new(0xff, 0x6e, 0x67),
+                Rgb::new(0x5f, 0xfa, 0x68),
+                Rgb::new(0xff, 0xfc, 0x67),
+                Rgb::new(0x68, 0x71, 0xff),
+                Rgb::new(0xff, 0x77, 0xff),
+                Rgb::new(0x60, 0xfd, 0xff),
+                Rgb::new(0xff, 0xff, 0xff),
+            ],
+            foreground: Rgb::new(0xc7, 0xc7, 0xc7),
+            background: Rgb::new(0x00, 0x00, 0x00),
+        }
+    }
+}
+
+impl Palette {
+    /// The profile keys a palette is read from.
+    pub fn profile_keys() -> Vec<String> {
+        (0..16)
+            .map(|i| format!("Ansi {i} Color"))
+            .chain([
+                "Foreground Color".to_string(),
+                "Background Color".to_string(),
+            ])
+            .collect()
+    }
+
+    /// Build a palette from profile properties. Missing colors keep their defaults.
+    pub fn from_profile(properties: &Map<String, Value>) -> Self {
+        let mut palette = Self::default();
+        let color = |key: &str| properties.get(key).and_then(Rgb::from_profile_color);
+        for (i, ansi) in palette.ansi.iter_mut().enumerate() {
+            if let Some(rgb) = color(&format!("Ansi {i} Color")) {
+                *ansi = rgb;
+            }
+        }
+        if let Some(rgb) = color("Foreground Color") {
+            palette.foreground = rgb;
+        }
+        if let Some(rgb) = color("Background Color") {
+            palette.background = rgb;
+        }
+        palette
+    }
+
+    /// The color of a 256-color palette index.
+    ///
+    /// Indices 0-15 come from the profile; 16-231 are the 6×6×6 color cube and 232-255 the
+    /// gray ramp, as in xterm.
+    ///
+    /// # Example
+    /// ```rust
+    /// use iterm2_api::style::{Palette, Rgb};
+    ///
+    /// let palette = Palette::default();
+    /// assert_eq!(palette.standard(196), Rgb::new(0xff, 0x00, 0x00));
+    /// assert_eq!(palette.standard(244), Rgb::new(0x80, 0x80, 0x80));
+    /// ```
+    pub fn standard(&self, index: u32) -> Rgb {
+        match index {
+            0..16 => self.ansi[index as usize],
+            16..232 => {
+                let level = |n: u32| if n == 0 { 0 } else { (55 + n * 40) as u8 };
+                let n = index - 16;
+                Rgb::new(level(n / 36), level(n / 6 % 6), level(n % 6))
+            }
+            232..256 => {
+                let gray = (8 + (index - 232) * 10) as u8;
+                Rgb::new(gray, gray, gray)
+            }
+            _ => self.foreground,
+        }
+    }
+}
+
+/// A hyperlink attached to a cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hyperlink {
+    pub url: String,
+    /// The OSC 8 `id` parameter, shared by cells of the same link
+    pub identifier: String,
+}
+
+/// The decoded style of one cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CellAttributes {
+    /// Foreground color as drawn, with `inverse` already applied
+    pub foreground: Rgb,
+    /// Background color as drawn, with `inverse` already applied
+    pub background: Rgb,
+    pub bold: bool,
+    pub faint: bool,
+    pub italic: bool,
+    pub blink: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub invisible: bool,
+    pub inverse: bool,
+    pub guarded: bool,
+    /// Set if the cell is an image placeholder
+    pub image: Option<ImagePlaceholderType>,
+    pub underline_color: Option<Rgb>,
+    pub block_id: Option<String>,
+    pub hyperlink: Option<Hyperlink>,
+}
+
+impl CellAttributes {
+    /// Decode a style, resolving its colors against `palette`.
+    pub fn resolve(style: &CellStyle, palette: &Palette) -> Self {
+        let foreground = match &style.fgColor {
+            Some(FgColor::FgStandard(index)) => palette.standard(*index),
+            Some(FgColor::FgRgb(rgb)) => Rgb::from(rgb),
+            Some(FgColor::FgAlternate(alternate))
+                if alternate.enum_value() == Ok(AlternateColor::REVERSED_DEFAULT) =>
+            {
+                palette.background
+            }
+            _ => palette.foreground,
+        };
+        let background = match &style.bgColor {
+            Some(BgColor::BgStandard(index)) => palette.standard(*index),
+            Some(BgColor::BgRgb(rgb)) => Rgb::from(rgb),
+            Some(BgColor::BgAlternate(alternate))
+                if alternate.enum_value() == Ok(AlternateColor::REVERSED_DEFAULT) =>
+            {
+                palette.foreground
+            }
+            _ => palette.background,
+        };
+        let inverse = style.inverse();
+        let (foreground, background) = if inverse {
+            (background, foreground)
+        } else {
+            (foreground, background)
+        };
+
+        Self {
+            foreground,
+            background,
+            bold: style.bold(),
+            faint: style.faint(),
+            italic: style.italic(),
+            blink: style.blink(),
+            underline: style.underline(),
+            strikethrough: style.strikethrough(),
+            invisible: style.invisible(),
+            inverse,
+            guarded: style.guarded(),
+            image: style
+                .image
+                .and_then(|image| image.enum_value().ok())
+                .filter(|image| *image != ImagePlaceholderType::NONE),
+            underline_color: style.underlineColor.as_ref().map(Rgb::from),
+            block_id: style.blockID.clone(),
+            hyperlink: style.url.as_ref().map(|url| Hyperlink {
+                url: url.url().to_string(),
+                identifier: url.identifier().to_string(),
+            }),
+        }
+    }
+}
+
+/// Expand run-length encoded styles to one style per cell.
+///
+/// A style without `repeats` counts once.
+///
+/// # Example
+/// ```rust
+/// use iterm2_api::generated::api::CellStyle;
+/// use iterm2_api::style::expand_styles;
+///
+/// let mut bold = CellStyle::new();
+/// bold.set_bold(true);
+/// bold.set_repeats(3);
+/// let mut plain = CellStyle::new();
+/// plain.set_repeats(2);
+///
+/// let styles = [bold, plain];
+/// let cells = expand_styles(&styles);
+/// assert_eq!(cells.len(), 5);
+/// assert!(cells[2].bold() && !cells[3].bold());
+/// ```
+pub fn expand_styles(styles: &[CellStyle]) -> Vec<&CellStyle> {
+    styles
+        .iter()
+        .flat_map(|style| std::iter::repeat_n(style, style.repeats().max(1) as usize))
+        .collect()
+}
+
+/// Expand run-length encoded styles and resolve them against `palette`, one entry per cell.
+pub fn decode_styles(styles: &[CellStyle], palette: &Palette) -> Vec<CellAttributes> {
+    let mut cells = Vec::new();
+    for style in styles {
+        let attributes = CellAttributes::resolve(style, palette);
+        cells.extend(std::iter::repeat_n(
+            attributes,
+            style.repeats().max(1) as usize,
+        ));
+    }
+    cells
+}
+
+impl BufferLine {
+    /// Decode this line's styles, one per cell. Empty unless the line was read with styles.
+    pub fn attributes(&self, palette: &Palette) -> Vec<CellAttributes> {
+        decode_styles(&self.styles, palette)
+    }
+}
+
+impl ITerm2Connection {
+    /// Read the colors of a session's profile.
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::buffer::BufferRange;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let palette = connection.get_palette("active").await?;
+    /// let buffer = connection
+    ///     .get_buffer("active", BufferRange::Screen, true)
+    ///     .await?;
+    /// for line in &buffer.lines {
+    ///     let bold = line.attributes(&palette).iter().filter(|cell| cell.bold).count();
+    ///     println!("{bold} bold cells: {}", line.text);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_palette(&mut self, session_id: &str) -> Result<Palette> {
+        let keys = Palette::profile_keys();
+        let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+        let properties = self.get_profile_properties(session_id, &keys).await?;
+        Ok(Palette::from_profile(&properties))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rgb(red: u32, green: u32, blue: u32) -> RGBColor {
+        let mut color = RGBColor::new();
+        color.set_red(red);
+        color.set_green(green);
+        color.set_blue(blue);
+        color
+    }
+
+    #[test]
+    fn expand_styles_counts_missing_repeats_once() {
+        let mut italic = CellStyle::new();
+        italic.set_italic(true);
+        let mut plain = CellStyle::new();
+        plain.set_repeats(2);
+
+        let styles = [italic, plain];
+        let cells = expand_styles(&styles);
+        assert_eq!(cells.len(), 3);
+        assert!(cells[0].italic());
+        assert!(!cells[1].italic() && !cells[2].italic());
+        assert!(expand_styles(&[]).is_empty());
+    }
+
+    #[test]
+    fn colors_resolve_against_the_palette() {
+        let palette = Palette::default();
+
+        let mut standard = CellStyle::new();
+        standard.set_fgStandard(1);
+        standard.set_bgStandard(21);
+        standard.set_repeats(2);
+        let mut true_color = CellStyle::new();
+        true_color.set_fgRgb(rgb(1, 2, 300));
+        true_color.set_bgAlternate(AlternateColor::REVERSED_DEFAULT);
+        let defaults = CellStyle::new();
+
+        let cells = decode_styles(&[standard, true_color, defaults], &palette);
+        assert_eq!(cells.len(), 4);
+        assert_eq!(cells[1].foreground, palette.ansi[1]);
+        assert_eq!(cells[1].background, Rgb::new(0x00, 0x00, 0xff));
+        assert_eq!(cells[2].foreground, Rgb::new(1, 2, 255));
+        assert_eq!(cells[2].background, palette.foreground);
+        assert_eq!(cells[3].foreground, palette.foreground);
+        assert_eq!(cells[3].background, palette.background);
+    }
+
+    #[test]
+    fn inverse_swaps_colors() {
+        let palette = Palette::default();
+        let mut style = CellStyle::new();
+        style.set_fgStandard(2);
+        style.set_inverse(true);
+
+        let cell = CellAttributes::resolve(&style, &palette);
+        assert!(cell.inverse);
+        assert_eq!(cell.foreground, palette.background);
+        assert_eq!(cell.background, palette.ansi[2]);
+
+        let mut style = CellStyle::new();
+        style.set_fgAlternate(AlternateColor::REVERSED_DEFAULT);
+        style.set_inverse(true);
+        let cell = CellAttributes::resolve(&style, &palette);
+        assert_eq!(cell.foreground, palette.background);
+        assert_eq!(cell.background, palette.background);
+    }
+
+    #[test]
+    fn attributes_are_copied() {
+        let mut style = CellStyle::new();
+        style.set_bold(true);
+        style.set_underline(true);
+        style.underlineColor = protobuf::MessageField::some(rgb(10, 20, 30));
+        style.set_image(ImagePlaceholderType::NONE);
+        style.set_blockID("b1".to_string());
+        let url = style.url.mut_or_insert_default();
+        url.set_url("https://example.com".to_string());
+        url.set_identifier("7".to_string());
+
+        let cell = CellAttributes::resolve(&style, &Palette::default());
+        assert!(cell.bold && cell.underline && !cell.italic);
+        assert_eq!(cell.underline_color, Some(Rgb::new(10, 20, 30)));
+        assert_eq!(cell.image, None);
+        assert_eq!(cell.block_id.as_deref(), Some("b1"));
+        assert_eq!(
+            cell.hyperlink,
+            Some(Hyperlink {
+                url: "https://example.com".to_string(),
+                identifier: "7".to_string(),
+            })
+        );
+
+        style.set_image(ImagePlaceholderType::KITTY);
+        let cell = CellAttributes::resolve(&style, &Palette::default());
+        assert_eq!(cell.image, Some(ImagePlaceholderType::KITTY));
+    }
+
+    #[test]
+    fn palette_from_profile() {
+        let mut properties = Map::new();
+        properties.insert(
+            "Ansi 4 Color".to_string(),
+            json!({"Red Component": 0.0, "Green Component": 0.5, "Blue Component": 2.0}),
+        );
+        properties.insert(
+            "Foreground Color".to_string(),
+            json!({"Red Component": 1.0, "Green Component": 1.0}),
+        );
+        let palette = Palette::from_profile(&properties);
+        assert_eq!(palette.ansi[4], Rgb::new(0, 128, 255));
+        // Incomplete colors are ignored
+        assert_eq!(palette.foreground, Palette::default().foreground);
+        assert_eq!(Palette::profile_keys().len(), 18);
+    }
+
+    #[test]
+    fn profile_colors_round_trip() {
+        let color = Rgb::new(0xd0, 0x40, 0x00);
+        let value = color.to_profile_color();
+        assert_eq!(value["Blue Component"], json!(0.0));
+        assert_eq!(value["Alpha Component"], json!(1.0));
+        assert_eq!(value["Color Space"], json!("sRGB"));
+        assert_eq!(Rgb::from_profile_color(&value), Some(color));
+        assert_eq!(color.hex(), "#d04000");
+    }
+
+    #[test]
+    fn standard_colors() {
+        let palette = Palette::default();
+        assert_eq!(palette.standard(15), palette.ansi[15]);
+        assert_eq!(palette.standard(16), Rgb::new(0, 0, 0));
+        assert_eq!(palette.standard(231), Rgb::new(255, 255, 255));
+        assert_eq!(palette.standard(232), Rgb::new(8, 8, 8));
+        assert_eq!(palette.standard(255), Rgb::new(238, 238, 238));
+        assert_eq!(palette.standard(256), palette.foreground);
+        assert_eq!(Rgb::new(255, 8, 0).hex(), "#ff0800");
+    }
+}
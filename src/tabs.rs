@@ -0,0 +1,503 @@
+//! Reordering tabs within a window and moving them between windows.
+//!
+//! `ReorderTabsRequest` expects, for every affected window, the complete ordered list of tabs the
+//! window should end up with. `TabOrder` starts from the current layout, applies edits locally,
+//! and computes those lists so callers only describe the change they want.
+
+use crate::connection::ITerm2Connection;
+use crate::error::{Error, Result};
+use crate::generated::api::list_sessions_response::Tab;
+use crate::generated::api::*;
+use crate::variables::VariableTarget;
+use serde_json::Value;
+
+/// The tab order of every window, edited locally before being sent to iTerm2.
+///
+/// # Example
+/// ```
+/// use iterm2_api::generated::api::ListSessionsResponse;
+/// use iterm2_api::generated::api::list_sessions_response::{Tab, Window};
+/// use iterm2_api::tabs::TabOrder;
+///
+/// let mut layout = ListSessionsResponse::new();
+/// for (window_id, tab_ids) in [("w1", ["1", "2", "3"]), ("w2", ["4", "5", "6"])] {
+///     let mut window = Window::new();
+///     window.set_window_id(window_id.to_string());
+///     for tab_id in tab_ids {
+///         let mut tab = Tab::new();
+///         tab.set_tab_id(tab_id.to_string());
+///         window.tabs.push(tab);
+///     }
+///     layout.windows.push(window);
+/// }
+///
+/// let mut order = TabOrder::from_layout(&layout);
+/// order.move_tab("3", 0)?;
+/// order.move_tab_to_window("5", "w1", None)?;
+///
+/// assert_eq!(order.window_tabs("w1").unwrap(), ["3", "1", "2", "5"]);
+/// assert_eq!(order.window_tabs("w2").unwrap(), ["4", "6"]);
+/// assert_eq!(order.assignments().len(), 2);
+/// # Ok::<(), iterm2_api::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TabOrder {
+    windows: Vec<(String, Vec<String>)>,
+    original: Vec<(String, Vec<String>)>,
+}
+
+impl TabOrder {
+    /// Capture the current tab order of every window.
+    pub fn from_layout(layout: &ListSessionsResponse) -> Self {
+        let windows: Vec<(String, Vec<String>)> = layout
+            .windows
+            .iter()
+            .map(|window| {
+                let tab_ids = window
+                    .tabs
+                    .iter()
+                    .map(|tab| tab.tab_id().to_string())
+                    .collect();
+                (window.window_id().to_string(), tab_ids)
+            })
+            .collect();
+        Self {
+            original: windows.clone(),
+            windows,
+        }
+    }
+
+    /// The tabs of a window in their current (edited) order.
+    pub fn window_tabs(&self, window_id: &str) -> Option<&[String]> {
+        self.windows
+            .iter()
+            .find(|(id, _)| id == window_id)
+            .map(|(_, tabs)| tabs.as_slice())
+    }
+
+    /// The window a tab currently (after edits) belongs to.
+    pub fn window_of(&self, tab_id: &str) -> Option<&str> {
+        self.windows
+            .iter()
+            .find(|(_, tabs)| tabs.iter().any(|id| id == tab_id))
+            .map(|(id, _)| id.as_str())
+    }
+
+    /// Move a tab to `index` within its window. Indexes past the end move it to the end.
+    ///
+    /// # Errors
+    /// Returns `Error::TabNotFound` if no window contains the tab
+    pub fn move_tab(&mut self, tab_id: &str, index: usize) -> Result<()> {
+        let window_id = self
+            .window_of(tab_id)
+            .ok_or_else(|| Error::TabNotFound(tab_id.to_string()))?
+            .to_string();
+        self.move_tab_to_window(tab_id, &window_id, Some(index))
+    }
+
+    /// Move a tab into another window.
+    ///
+    /// # Arguments
+    /// * `tab_id` - The tab to move
+    /// * `window_id` - The destination window
+    /// * `index` - The position in the destination window, or `None` to append
+    ///
+    /// # Errors
+    /// Returns `Error::TabNotFound` or `Error::WindowNotFound` for unknown IDs
+    pub fn move_tab_to_window(
+        &mut self,
+        tab_id: &str,
+        window_id: &str,
+        index: Option<usize>,
+    ) -> Result<()> {
+        if self.window_of(tab_id).is_none() {
+            return Err(Error::TabNotFound(tab_id.to_string()));
+        }
+        let target = self.window_index(window_id)?;
+        self.remove_tab(tab_id);
+
+        let tabs = &mut self.windows[target].1;
+        let index = index.unwrap_or(tabs.len()).min(tabs.len());
+        tabs.insert(index, tab_id.to_string());
+        Ok(())
+    }
+
+    /// Give a window an explicit list of tabs.
+    ///
+    /// The listed tabs come first, in the given order, and are removed from whatever window held
+    /// them. Tabs already in the window but not listed keep their relative order after them.
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidAssignment` if a tab is listed twice, or a not-found error for
+    /// unknown IDs
+    pub fn assign(&mut self, window_id: &str, tab_ids: &[&str]) -> Result<()> {
+        let target = self.window_index(window_id)?;
+        for (i, tab_id) in tab_ids.iter().enumerate() {
+            if tab_ids[..i].contains(tab_id) {
+                return Err(Error::InvalidAssignment(format!(
+                    "tab {tab_id} listed more than once"
+                )));
+            }
+            if self.window_of(tab_id).is_none() {
+                return Err(Error::TabNotFound(tab_id.to_string()));
+            }
+        }
+
+        for tab_id in tab_ids {
+            self.remove_tab(tab_id);
+        }
+        let tabs = &mut self.windows[target].1;
+        let rest = std::mem::take(tabs);
+        tabs.extend(tab_ids.iter().map(|id| id.to_string()));
+        tabs.extend(rest);
+        Ok(())
+    }
+
+    /// Stable-sort the tabs of a window by a key derived from each tab ID.
+    ///
+    /// # Errors
+    /// Returns `Error::WindowNotFound` if the window is unknown
+    pub fn sort_window_by_key<K: Ord>(
+        &mut self,
+        window_id: &str,
+        mut key: impl FnMut(&str) -> K,
+    ) -> Result<()> {
+        let target = self.window_index(window_id)?;
+        self.windows[target].1.sort_by_cached_key(|id| key(id));
+        Ok(())
+    }
+
+    /// The assignments needed to go from the original order to the edited one.
+    ///
+    /// Only windows whose tab list changed are included. Windows left without any tabs are
+    /// omitted, since an assignment cannot be empty.
+    pub fn assignments(&self) -> Vec<reorder_tabs_request::Assignment> {
+        self.windows
+            .iter()
+            .zip(&self.original)
+            .filter(|((_, tabs), (_, original))| !tabs.is_empty() && tabs != original)
+            .map(|((window_id, tabs), _)| {
+                let mut assignment = reorder_tabs_request::Assignment::new();
+                assignment.set_window_id(window_id.clone());
+                assignment.tab_ids = tabs.clone();
+                assignment
+            })
+            .collect()
+    }
+
+    fn window_index(&self, window_id: &str) -> Result<usize> {
+        self.windows
+            .iter()
+            .position(|(id, _)| id == window_id)
+            .ok_or_else(|| Error::WindowNotFound(window_id.to_string()))
+    }
+
+    fn remove_tab(&mut self, tab_id: &str) {
+        for (_, tabs) in &mut self.windows {
+            tabs.retain(|id| id != tab_id);
+        }
+    }
+}
+
+/// What to sort tabs by in `ITerm2Connection::sort_tabs()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TabSortKey {
+    /// The tab's title.
+    Title,
+    /// The working directory of the tab's current session.
+    WorkingDirectory,
+    /// Any tab-scoped variable, e.g. `"user.project"` or `"currentSession.user.project"`.
+    Variable(String),
+}
+
+impl TabSortKey {
+    fn variable_name(&self) -> &str {
+        match self {
+            TabSortKey::Title => "title",
+            TabSortKey::WorkingDirectory => "currentSession.path",
+            TabSortKey::Variable(name) => name,
+        }
+    }
+}
+
+impl ITerm2Connection {
+    /// Reorder tabs within and across windows.
+    ///
+    /// Each assignment lists the complete, ordered set of tabs a window should contain.
+    /// Windows without an assignment are left untouched. Most callers should use the
+    /// higher-level methods below, which compute the assignments from the current layout.
+    ///
+    /// # Arguments
+    /// * `assignments` - The desired tab order for each affected window
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidAssignment`, `Error::WindowNotFound` or `Error::TabNotFound` if
+    /// iTerm2 rejects the assignments
+    pub async fn reorder_tabs(
+        &mut self,
+        assignments: Vec<reorder_tabs_request::Assignment>,
+    ) -> Result<()> {
+        let window_ids = assignments
+            .iter()
+            .map(|assignment| assignment.window_id())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let mut request = ReorderTabsRequest::new();
+        request.assignments = assignments;
+
+        let mut message = ClientOriginatedMessage::new();
+        message.set_reorder_tabs_request(request);
+
+        let response = self.call(message).await?;
+
+        if response.has_reorder_tabs_response() {
+            match response.reorder_tabs_response().status() {
+                reorder_tabs_response::Status::OK => Ok(()),
+                reorder_tabs_response::Status::INVALID_ASSIGNMENT => Err(Error::InvalidAssignment(
+                    format!("rejected assignment for windows {window_ids}"),
+                )),
+                reorder_tabs_response::Status::INVALID_WINDOW_ID => {
+                    Err(Error::WindowNotFound(window_ids))
+                }
+                reorder_tabs_response::Status::INVALID_TAB_ID => Err(Error::TabNotFound(format!(
+                    "unknown tab in assignment for windows {window_ids}"
+                ))),
+            }
+        } else {
+            Err(Error::Api("Expected reorder tabs response".to_string()))
+        }
+    }
+
+    /// Send the assignments computed by a `TabOrder`. Does nothing if nothing changed.
+    ///
+    /// # Errors
+    /// See `reorder_tabs()`
+    pub async fn apply_tab_order(&mut self, order: &TabOrder) -> Result<()> {
+        let assignments = order.assignments();
+        if assignments.is_empty() {
+            return Ok(());
+        }
+        self.reorder_tabs(assignments).await
+    }
+
+    /// Move a tab to `index` within its window.
+    ///
+    /// # Errors
+    /// Returns `Error::TabNotFound` if the tab doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let windows = connection.get_windows().await?;
+    /// let last_tab = windows[0].tabs.last().unwrap().tab_id().to_string();
+    ///
+    /// // Make the last tab the first one
+    /// connection.move_tab(&last_tab, 0).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn move_tab(&mut self, tab_id: &str, index: usize) -> Result<()> {
+        let mut order = TabOrder::from_layout(&self.get_layout().await?);
+        order.move_tab(tab_id, index)?;
+        self.apply_tab_order(&order).await
+    }
+
+    /// Move a tab into another window.
+    ///
+    /// # Arguments
+    /// * `tab_id` - The tab to move
+    /// * `window_id` - The destination window
+    /// * `index` - The position in the destination window, or `None` to append
+    ///
+    /// # Errors
+    /// Returns `Error::TabNotFound` or `Error::WindowNotFound` for unknown IDs
+    pub async fn move_tab_to_window(
+        &mut self,
+        tab_id: &str,
+        window_id: &str,
+        index: Option<usize>,
+    ) -> Result<()> {
+        let mut order = TabOrder::from_layout(&self.get_layout().await?);
+        order.move_tab_to_window(tab_id, window_id, index)?;
+        self.apply_tab_order(&order).await
+    }
+
+    /// Apply explicit tab lists to one or more windows.
+    ///
+    /// See `TabOrder::assign()` for how partial lists are completed.
+    ///
+    /// # Arguments
+    /// * `assignments` - Pairs of window ID and the tabs it should start with
+    ///
+    /// # Errors
+    /// Returns `Error::InvalidAssignment` or a not-found error if the assignments are invalid
+    pub async fn assign_tabs(&mut self, assignments: &[(&str, &[&str])]) -> Result<()> {
+        let mut order = TabOrder::from_layout(&self.get_layout().await?);
+        for (window_id, tab_ids) in assignments {
+            order.assign(window_id, tab_ids)?;
+        }
+        self.apply_tab_order(&order).await
+    }
+
+    /// Sort the tabs of a window by a key computed from each tab.
+    ///
+    /// The sort is stable, so tabs with equal keys keep their relative order.
+    ///
+    /// # Errors
+    /// Returns `Error::WindowNotFound` if the window doesn't exist
+    pub async fn sort_tabs_by<K: Ord>(
+        &mut self,
+        window_id: &str,
+        mut key: impl FnMut(&Tab) -> K,
+    ) -> Result<()> {
+        let layout = self.get_layout().await?;
+        let window = crate::tree::find_window(&layout, window_id)
+            .ok_or_else(|| Error::WindowNotFound(window_id.to_string()))?;
+        let keys: Vec<(String, K)> = window
+            .tabs
+            .iter()
+            .map(|tab| (tab.tab_id().to_string(), key(tab)))
+            .collect();
+
+        let mut order = TabOrder::from_layout(&layout);
+        sort_by_keys(&mut order, window_id, &keys)?;
+        self.apply_tab_order(&order).await
+    }
+
+    /// Sort the tabs of a window by title, working directory or a variable.
+    ///
+    /// Values are compared as strings; tabs where the value is unset sort first.
+    ///
+    /// # Errors
+    /// Returns `Error::WindowNotFound` if the window doesn't exist, or `Error::Api` if a
+    /// variable can't be read
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::tabs::TabSortKey;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let windows = connection.get_windows().await?;
+    /// connection
+    ///     .sort_tabs(windows[0].window_id(), TabSortKey::WorkingDirectory)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn sort_tabs(&mut self, window_id: &str, key: TabSortKey) -> Result<()> {
+        let layout = self.get_layout().await?;
+        let window = crate::tree::find_window(&layout, window_id)
+            .ok_or_else(|| Error::WindowNotFound(window_id.to_string()))?;
+
+        let mut keys = Vec::with_capacity(window.tabs.len());
+        for tab in &window.tabs {
+            let value = self
+                .get_variable(VariableTarget::Tab(tab.tab_id()), key.variable_name())
+                .await?;
+            let value = match value {
+                Value::String(value) => value,
+                Value::Null => String::new(),
+                other => other.to_string(),
+            };
+            keys.push((tab.tab_id().to_string(), value));
+        }
+
+        let mut order = TabOrder::from_layout(&layout);
+        sort_by_keys(&mut order, window_id, &keys)?;
+        self.apply_tab_order(&order).await
+    }
+}
+
+/// Sort the tabs of a window by keys computed up front. Tabs without a key sort first.
+fn sort_by_keys<K: Ord>(order: &mut TabOrder, window_id: &str, keys: &[(String, K)]) -> Result<()> {
+    order.sort_window_by_key(window_id, |tab_id| {
+        keys.iter().find(|(id, _)| id == tab_id).map(|(_, key)| key)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generated::api::list_sessions_response::Window;
+
+    fn layout(windows: &[(&str, &[&str])]) -> ListSessionsResponse {
+        let mut layout = ListSessionsResponse::new();
+        for (window_id, tab_ids) in windows {
+            let mut window = Window::new();
+            window.set_window_id(window_id.to_string());
+            for tab_id in *tab_ids {
+                let mut tab = Tab::new();
+                tab.set_tab_id(tab_id.to_string());
+                window.tabs.push(tab);
+            }
+            layout.windows.push(window);
+        }
+        layout
+    }
+
+    fn keys(keys: &[(&str, &str)]) -> Vec<(String, String)> {
+        keys.iter()
+            .map(|(id, key)| (id.to_string(), key.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn sort_is_stable_and_puts_missing_keys_first() {
+        let mut order = TabOrder::from_layout(&layout(&[("w1", &["1", "2", "3", "4"])]));
+        let keys = keys(&[("1", "src"), ("2", "docs"), ("4", "docs")]);
+        sort_by_keys(&mut order, "w1", &keys).unwrap();
+        assert_eq!(order.window_tabs("w1").unwrap(), ["3", "2", "4", "1"]);
+
+        let assignments = order.assignments();
+        assert_eq!(assignments.len(), 1);
+        assert_eq!(assignments[0].window_id(), "w1");
+        assert_eq!(assignments[0].tab_ids, ["3", "2", "4", "1"]);
+
+        assert!(matches!(
+            sort_by_keys(&mut order, "w9", &keys),
+            Err(Error::WindowNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn sorted_windows_need_no_assignment() {
+        let mut order = TabOrder::from_layout(&layout(&[("w1", &["1", "2"]), ("w2", &["3"])]));
+        sort_by_keys(&mut order, "w1", &keys(&[("1", "a"), ("2", "b")])).unwrap();
+        assert!(order.assignments().is_empty());
+    }
+
+    #[test]
+    fn assign_completes_partial_lists() {
+        let mut order = TabOrder::from_layout(&layout(&[("w1", &["1", "2", "3"]), ("w2", &["4"])]));
+        order.assign("w2", &["2", "4"]).unwrap();
+        assert_eq!(order.window_tabs("w1").unwrap(), ["1", "3"]);
+        assert_eq!(order.window_tabs("w2").unwrap(), ["2", "4"]);
+        assert_eq!(order.window_of("2"), Some("w2"));
+
+        assert!(matches!(
+            order.assign("w1", &["1", "1"]),
+            Err(Error::InvalidAssignment(_))
+        ));
+        assert!(matches!(
+            order.assign("w1", &["9"]),
+            Err(Error::TabNotFound(_))
+        ));
+        assert!(matches!(order.move_tab("9", 0), Err(Error::TabNotFound(_))));
+    }
+
+    #[test]
+    fn emptied_windows_are_left_out_of_assignments() {
+        let mut order = TabOrder::from_layout(&layout(&[("w1", &["1"]), ("w2", &["2"])]));
+        order.move_tab_to_window("1", "w2", Some(0)).unwrap();
+        let assignments = order.assignments();
+        assert_eq!(assignments.len(), 1);
+        assert_eq!(assignments[0].window_id(), "w2");
+        assert_eq!(assignments[0].tab_ids, ["1", "2"]);
+    }
+}
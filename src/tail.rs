@@ -0,0 +1,410 @@
+//! Following a session's output line by line.
+//!
+//! `Tail` delivers each logical line once, after it is complete (the cursor has moved past it).
+//! It relies on iTerm2's stable line numbering: a line keeps its number when older lines are
+//! dropped from history, so "everything after line N" stays meaningful between reads.
+//!
+//! The API doesn't say when the alternate screen is in use. Instead, `Tail` remembers the lines it
+//! delivered that are still on screen; if any of them are redrawn in place (a full-screen program
+//! switched to the alternate screen, or output is being redrawn with cursor movement), delivery
+//! pauses until they are restored or scroll out of view. Lines written in the meantime are then
+//! delivered in order.
+
+use crate::buffer::{BufferLine, BufferRange, LogicalLine, join_wrapped};
+use crate::connection::ITerm2Connection;
+use crate::error::Result;
+use crate::generated::api::NotificationType;
+use crate::notification::is_screen_update;
+use crate::properties::LineCounts;
+use futures_util::Stream;
+use std::collections::VecDeque;
+
+/// Reads new lines from a session as they are written.
+#[derive(Debug, Clone)]
+pub struct Tail {
+    session_id: String,
+    /// The first line not yet delivered
+    next_line: i64,
+    /// Lines already delivered that may still be on screen, oldest first
+    delivered: VecDeque<(i64, String)>,
+    pending: VecDeque<LogicalLine>,
+    lost_lines: u64,
+}
+
+impl Tail {
+    /// Start following a session from the cursor's line.
+    ///
+    /// Output already on screen above the cursor is not delivered. This subscribes the
+    /// connection to screen updates for the session; call `stop()` when done. Only this
+    /// session's screen updates are taken from the connection while waiting; other notifications
+    /// stay queued for other readers.
+    ///
+    /// # Arguments
+    /// * `session_id` - The session to follow. Use a real session ID, not `"active"`.
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::tail::Tail;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let mut tail = Tail::start(&mut connection, "SESSION-ID").await?;
+    /// loop {
+    ///     let line = tail.next(&mut connection).await?;
+    ///     println!("{:>8} {}", line.y, line.text);
+    /// }
+    /// # }
+    /// ```
+    pub async fn start(connection: &mut ITerm2Connection, session_id: &str) -> Result<Self> {
+        connection
+            .subscribe(NotificationType::NOTIFY_ON_SCREEN_UPDATE, Some(session_id))
+            .await?;
+        let buffer = connection
+            .get_buffer(session_id, BufferRange::Screen, false)
+            .await?;
+        let next_line = buffer.cursor.y();
+        let delivered = buffer
+            .lines
+            .into_iter()
+            .filter(|line| line.y < next_line)
+            .map(|line| (line.y, line.text))
+            .collect();
+        Ok(Self {
+            session_id: session_id.to_string(),
+            next_line,
+            delivered,
+            pending: VecDeque::new(),
+            lost_lines: 0,
+        })
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// Screen lines that were dropped from history before they could be read.
+    ///
+    /// This grows when output arrives faster than it is read and the scrollback limit is
+    /// reached.
+    pub fn lost_lines(&self) -> u64 {
+        self.lost_lines
+    }
+
+    /// Wait for the next complete line.
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session closes, or `Error::Connection` if the
+    /// connection is closed
+    pub async fn next(&mut self, connection: &mut ITerm2Connection) -> Result<LogicalLine> {
+        loop {
+            if let Some(line) = self.pending.pop_front() {
+                return Ok(line);
+            }
+            // The read below sees everything these updates reported
+            connection.discard_notifications_where(|n| is_screen_update(n, &self.session_id));
+            self.read(connection).await?;
+            if !self.pending.is_empty() {
+                continue;
+            }
+            connection
+                .next_notification_where(|n| is_screen_update(n, &self.session_id))
+                .await?;
+        }
+    }
+
+    /// Turn the tail into a stream of lines that owns the connection.
+    ///
+    /// The stream ends after the first error, unsubscribing from screen updates first. Dropping
+    /// the stream closes the connection, which ends its subscriptions too.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::tail::Tail;
+    /// # use futures_util::StreamExt;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let tail = Tail::start(&mut connection, "SESSION-ID").await?;
+    /// let mut lines = Box::pin(tail.into_stream(connection));
+    /// while let Some(line) = lines.next().await {
+    ///     println!("{}", line?.text);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn into_stream(
+        self,
+        connection: ITerm2Connection,
+    ) -> impl Stream<Item = Result<LogicalLine>> {
+        futures_util::stream::unfold(Some((self, connection)), |state| async move {
+            let (mut tail, mut connection) = state?;
+            match tail.next(&mut connection).await {
+                Ok(line) => Some((Ok(line), Some((tail, connection)))),
+                Err(e) => {
+                    let session_id = tail.session_id.clone();
+                    if let Err(stop_error) = tail.stop(&mut connection).await {
+                        tracing::warn!(
+                            "Failed to unsubscribe from screen updates of {session_id}: {stop_error}"
+                        );
+                    }
+                    Some((Err(e), None))
+                }
+            }
+        })
+    }
+
+    /// Unsubscribe from the session's screen updates and discard any that are still queued.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if unsubscribing fails
+    pub async fn stop(self, connection: &mut ITerm2Connection) -> Result<()> {
+        let stopped = connection
+            .unsubscribe(
+                NotificationType::NOTIFY_ON_SCREEN_UPDATE,
+                Some(&self.session_id),
+            )
+            .await;
+        connection.discard_notifications_where(|n| is_screen_update(n, &self.session_id));
+        stopped
+    }
+
+    /// Read lines written since the last read and queue the complete ones.
+    async fn read(&mut self, connection: &mut ITerm2Connection) -> Result<()> {
+        let counts = connection.get_line_counts(&self.session_id).await?;
+        let Some((first, end)) = self.unread_range(&counts) else {
+            return Ok(());
+        };
+        let buffer = connection
+            .get_buffer(&self.session_id, BufferRange::lines(first, end), false)
+            .await?;
+        self.apply(first, &buffer.lines, buffer.cursor.y());
+        Ok(())
+    }
+
+    /// The lines to read next: from the oldest delivered line still on screen, or the first
+    /// undelivered line, to the end of the buffer. Lines dropped from history before they were
+    /// read are counted as lost.
+    fn unread_range(&mut self, counts: &LineCounts) -> Option<(i64, i64)> {
+        let end = counts.overflow + counts.history + counts.grid;
+        let screen_top = end - counts.grid;
+
+        if self.next_line < counts.overflow {
+            self.lost_lines += (counts.overflow - self.next_line) as u64;
+            self.next_line = counts.overflow;
+        }
+        while self.delivered.front().is_some_and(|(y, _)| *y < screen_top) {
+            self.delivered.pop_front();
+        }
+
+        let first = self
+            .delivered
+            .front()
+            .map_or(self.next_line, |(y, _)| (*y).min(self.next_line))
+            .max(counts.overflow);
+        (first < end).then_some((first, end))
+    }
+
+    /// Queue the complete lines in `lines`, which start at line `first`.
+    ///
+    /// Nothing is queued while delivered lines are redrawn in place, unless they were cleared.
+    fn apply(&mut self, first: i64, lines: &[BufferLine], cursor: i64) {
+        let row = |y: i64| {
+            usize::try_from(y - first)
+                .ok()
+                .and_then(|index| lines.get(index))
+        };
+
+        let redrawn: Vec<i64> = self
+            .delivered
+            .iter()
+            .filter(|(y, text)| row(*y).is_none_or(|line| line.text != *text))
+            .map(|(y, _)| *y)
+            .collect();
+        if !redrawn.is_empty() {
+            let cleared = redrawn
+                .iter()
+                .all(|y| row(*y).is_none_or(|line| line.text.trim().is_empty()));
+            if !cleared {
+                return;
+            }
+            // The screen was erased without saving it to history; output restarts at the top
+            self.next_line = redrawn[0].min(self.next_line);
+            self.delivered.clear();
+        }
+
+        let rows: Vec<&BufferLine> = lines
+            .iter()
+            .filter(|line| line.y >= self.next_line && line.y < cursor)
+            .collect();
+        let Some(last_complete) = rows.iter().rposition(|line| !line.soft_wrapped) else {
+            return;
+        };
+        let complete: Vec<BufferLine> = rows[..=last_complete]
+            .iter()
+            .map(|line| (*line).clone())
+            .collect();
+
+        self.pending.extend(join_wrapped(&complete));
+        for line in complete {
+            self.next_line = line.y + 1;
+            self.delivered.push_back((line.y, line.text));
+        }
+    }
+}
+
+impl ITerm2Connection {
+    /// Stream the lines a session writes from now on.
+    ///
+    /// The stream owns the connection, and dropping it closes the connection. Use [`Tail`]
+    /// directly to keep making other requests between lines.
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound` if the session doesn't exist
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use futures_util::StreamExt;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let connection = ITerm2Connection::connect().await?;
+    /// let mut lines = Box::pin(connection.tail("SESSION-ID").await?);
+    /// while let Some(line) = lines.next().await {
+    ///     let line = line?;
+    ///     println!("{} {}", line.y, line.text);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn tail(
+        mut self,
+        session_id: &str,
+    ) -> Result<impl Stream<Item = Result<LogicalLine>>> {
+        let tail = Tail::start(&mut self, session_id).await?;
+        Ok(tail.into_stream(self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(y: i64, text: &str, soft_wrapped: bool) -> BufferLine {
+        BufferLine {
+            y,
+            text: text.to_string(),
+            soft_wrapped,
+            code_points_per_cell: Vec::new(),
+            styles: Vec::new(),
+        }
+    }
+
+    fn screen(first: i64, texts: &[&str]) -> Vec<BufferLine> {
+        texts
+            .iter()
+            .zip(first..)
+            .map(|(text, y)| line(y, text, false))
+            .collect()
+    }
+
+    /// A tail that has delivered `delivered`, starting at line 0.
+    fn tail(delivered: &[&str]) -> Tail {
+        Tail {
+            session_id: "s1".to_string(),
+            next_line: delivered.len() as i64,
+            delivered: delivered
+                .iter()
+                .zip(0..)
+                .map(|(text, y)| (y, text.to_string()))
+                .collect(),
+            pending: VecDeque::new(),
+            lost_lines: 0,
+        }
+    }
+
+    fn counts(overflow: i64, history: i64, grid: i64) -> LineCounts {
+        LineCounts {
+            overflow,
+            grid,
+            history,
+        }
+    }
+
+    fn pending(tail: &mut Tail) -> Vec<(i64, String)> {
+        tail.pending
+            .drain(..)
+            .map(|line| (line.y, line.text))
+            .collect()
+    }
+
+    #[test]
+    fn unchanged_screens_queue_nothing() {
+        let mut tail = tail(&["$ make", "ok"]);
+        assert_eq!(tail.unread_range(&counts(0, 0, 4)), Some((0, 4)));
+        tail.apply(0, &screen(0, &["$ make", "ok", "$ ", ""]), 2);
+        assert!(tail.pending.is_empty());
+        assert_eq!(tail.next_line, 2);
+    }
+
+    #[test]
+    fn complete_lines_are_queued_once() {
+        let mut tail = tail(&["$ make"]);
+        let mut lines = screen(0, &["$ make", "compiling a", "b", "done", "$ "]);
+        lines[1].soft_wrapped = true;
+        lines[3].soft_wrapped = true;
+        // "done" wraps onto the cursor's line, so it isn't complete yet
+        tail.apply(0, &lines, 4);
+        assert_eq!(pending(&mut tail), [(1, "compiling ab".to_string())]);
+        assert_eq!(tail.next_line, 3);
+
+        tail.apply(0, &lines, 4);
+        assert!(tail.pending.is_empty());
+    }
+
+    #[test]
+    fn truncated_history_counts_lost_lines() {
+        let mut tail = tail(&["a", "b"]);
+        // Ten lines scrolled off and were dropped before this read
+        assert_eq!(tail.unread_range(&counts(12, 0, 3)), Some((12, 15)));
+        assert_eq!(tail.lost_lines(), 10);
+        assert!(tail.delivered.is_empty());
+
+        tail.apply(12, &screen(12, &["m", "n", ""]), 14);
+        assert_eq!(
+            pending(&mut tail),
+            [(12, "m".to_string()), (13, "n".to_string())]
+        );
+        // Delivered lines still on screen are read again to notice redraws
+        assert_eq!(tail.unread_range(&counts(12, 0, 2)), Some((12, 14)));
+        assert_eq!(tail.unread_range(&counts(14, 0, 0)), None);
+    }
+
+    #[test]
+    fn redrawn_lines_pause_delivery() {
+        let mut tail = tail(&["a", "b"]);
+        // A full-screen program draws over the delivered lines
+        tail.apply(0, &screen(0, &["top - 12:00", "PID", "1 init"]), 3);
+        assert!(tail.pending.is_empty());
+        assert_eq!(tail.next_line, 2);
+
+        // Back on the main screen, output continues after the delivered lines
+        tail.apply(0, &screen(0, &["a", "b", "c", ""]), 3);
+        assert_eq!(pending(&mut tail), [(2, "c".to_string())]);
+    }
+
+    #[test]
+    fn cleared_screens_restart_at_the_top() {
+        let mut tail = tail(&["a", "b"]);
+        tail.apply(0, &screen(0, &["", "", ""]), 0);
+        assert!(tail.pending.is_empty());
+        assert_eq!(tail.next_line, 0);
+
+        tail.apply(0, &screen(0, &["x", "", ""]), 1);
+        assert_eq!(pending(&mut tail), [(0, "x".to_string())]);
+    }
+}
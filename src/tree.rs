@@ -0,0 +1,64 @@
+//! Helpers for walking the window/tab/split-pane hierarchy returned by `ListSessionsResponse`.
+
+use crate::generated::api::list_sessions_response::{Tab, Window};
+use crate::generated::api::split_tree_node::split_tree_link::Child;
+use crate::generated::api::*;
+
+/// Collect the sessions of a split tree in depth-first order (left-to-right, top-to-bottom).
+pub fn sessions(node: &SplitTreeNode) -> Vec<&SessionSummary> {
+    let mut result = Vec::new();
+    collect_sessions(node, &mut result);
+    result
+}
+
+fn collect_sessions<'a>(node: &'a SplitTreeNode, result: &mut Vec<&'a SessionSummary>) {
+    for link in &node.links {
+        match &link.child {
+            Some(Child::Session(session)) => result.push(session),
+            Some(Child::Node(child)) => collect_sessions(child, result),
+            None => {}
+        }
+    }
+}
+
+/// Collect the visible sessions of a tab, in pane order.
+pub fn tab_sessions(tab: &Tab) -> Vec<&SessionSummary> {
+    tab.root.as_ref().map(sessions).unwrap_or_default()
+}
+
+/// Find the window and tab containing a session.
+pub fn find_session<'a>(
+    layout: &'a ListSessionsResponse,
+    session_id: &str,
+) -> Option<(&'a Window, &'a Tab, &'a SessionSummary)> {
+    layout.windows.iter().find_map(|window| {
+        window.tabs.iter().find_map(|tab| {
+            tab_sessions(tab)
+                .into_iter()
+                .find(|session| session.unique_identifier() == session_id)
+                .map(|session| (window, tab, session))
+        })
+    })
+}
+
+/// Find the window containing a tab.
+pub fn find_tab<'a>(
+    layout: &'a ListSessionsResponse,
+    tab_id: &str,
+) -> Option<(&'a Window, &'a Tab)> {
+    layout.windows.iter().find_map(|window| {
+        window
+            .tabs
+            .iter()
+            .find(|tab| tab.tab_id() == tab_id)
+            .map(|tab| (window, tab))
+    })
+}
+
+/// Find a window by ID.
+pub fn find_window<'a>(layout: &'a ListSessionsResponse, window_id: &str) -> Option<&'a Window> {
+    layout
+        .windows
+        .iter()
+        .find(|window| window.window_id() == window_id)
+}
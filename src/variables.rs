@@ -0,0 +1,178 @@
+//! Reading and writing iTerm2 variables.
+//!
+//! Variables belong to the app, a window, a tab or a session, and their values travel as JSON.
+//! Scripts can define their own variables in any scope under the `user.` prefix.
+
+use crate::connection::ITerm2Connection;
+use crate::error::{Error, Result};
+use crate::generated::api::*;
+use serde_json::{Map, Value};
+
+/// The object whose variables are read or written.
+///
+/// iTerm2 variables live in one of four scopes. User-defined variables must be named with a
+/// `user.` prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariableTarget<'a> {
+    App,
+    Window(&'a str),
+    Tab(&'a str),
+    Session(&'a str),
+}
+
+impl VariableTarget<'_> {
+    fn apply(&self, request: &mut VariableRequest) {
+        match *self {
+            VariableTarget::App => request.set_app(true),
+            VariableTarget::Window(id) => request.set_window_id(id.to_string()),
+            VariableTarget::Tab(id) => request.set_tab_id(id.to_string()),
+            VariableTarget::Session(id) => request.set_session_id(id.to_string()),
+        }
+    }
+
+    fn id(&self) -> &str {
+        match *self {
+            VariableTarget::App => "app",
+            VariableTarget::Window(id) | VariableTarget::Tab(id) | VariableTarget::Session(id) => {
+                id
+            }
+        }
+    }
+}
+
+impl ITerm2Connection {
+    /// Get the value of a variable.
+    ///
+    /// # Arguments
+    /// * `target` - The scope to read from
+    /// * `name` - The variable name, e.g. `"path"` or `"user.project"`
+    ///
+    /// # Returns
+    /// The decoded JSON value, or `Value::Null` if the variable is unset
+    ///
+    /// # Errors
+    /// Returns `Error::SessionNotFound`, `Error::TabNotFound` or `Error::WindowNotFound` if the
+    /// target doesn't exist, or `Error::Api` for other failures
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::variables::VariableTarget;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let session = connection.create_window(None).await?;
+    ///
+    /// let path = connection
+    ///     .get_variable(VariableTarget::Session(session.unique_identifier()), "path")
+    ///     .await?;
+    /// println!("Working directory: {path}");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn get_variable(&mut self, target: VariableTarget<'_>, name: &str) -> Result<Value> {
+        let mut values = self.get_variables(target, &[name]).await?;
+        Ok(values.pop().unwrap_or(Value::Null))
+    }
+
+    /// Get the values of several variables in one request.
+    ///
+    /// # Returns
+    /// One decoded value per name, in the same order, with `Value::Null` for unset variables
+    ///
+    /// # Errors
+    /// See `get_variable()`
+    pub async fn get_variables(
+        &mut self,
+        target: VariableTarget<'_>,
+        names: &[&str],
+    ) -> Result<Vec<Value>> {
+        let mut request = VariableRequest::new();
+        target.apply(&mut request);
+        request.get = names.iter().map(|name| name.to_string()).collect();
+
+        let response = self.variable_request(target, request).await?;
+        response
+            .values
+            .iter()
+            .map(|value| Ok(serde_json::from_str(value)?))
+            .collect()
+    }
+
+    /// Get every variable defined in a scope.
+    ///
+    /// # Returns
+    /// A map from variable name to decoded value. Nested scopes (such as `currentSession` in a
+    /// tab) appear as nested objects.
+    ///
+    /// # Errors
+    /// See `get_variable()`
+    pub async fn get_all_variables(
+        &mut self,
+        target: VariableTarget<'_>,
+    ) -> Result<Map<String, Value>> {
+        match self.get_variable(target, "*").await? {
+            Value::Object(map) => Ok(map),
+            Value::Null => Ok(Map::new()),
+            other => Err(Error::Api(format!(
+                "Expected a dictionary of variables, got {other}"
+            ))),
+        }
+    }
+
+    /// Set the value of a variable.
+    ///
+    /// # Arguments
+    /// * `target` - The scope to write to. `"all"` is accepted as a window, tab or session ID.
+    /// * `name` - The variable name, which must begin with `user.`
+    /// * `value` - The new value
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if the name is invalid, or a not-found error if the target doesn't exist
+    pub async fn set_variable(
+        &mut self,
+        target: VariableTarget<'_>,
+        name: &str,
+        value: &Value,
+    ) -> Result<()> {
+        let mut set = variable_request::Set::new();
+        set.set_name(name.to_string());
+        set.set_value(value.to_string());
+
+        let mut request = VariableRequest::new();
+        target.apply(&mut request);
+        request.set.push(set);
+
+        self.variable_request(target, request).await?;
+        Ok(())
+    }
+
+    async fn variable_request(
+        &mut self,
+        target: VariableTarget<'_>,
+        request: VariableRequest,
+    ) -> Result<VariableResponse> {
+        let mut message = ClientOriginatedMessage::new();
+        message.set_variable_request(request);
+
+        let mut response = self.call(message).await?;
+
+        if !response.has_variable_response() {
+            return Err(Error::Api("Expected variable response".to_string()));
+        }
+        let variable_response = response.take_variable_response();
+        match variable_response.status() {
+            variable_response::Status::OK => Ok(variable_response),
+            variable_response::Status::SESSION_NOT_FOUND => {
+                Err(Error::SessionNotFound(target.id().to_string()))
+            }
+            variable_response::Status::TAB_NOT_FOUND => {
+                Err(Error::TabNotFound(target.id().to_string()))
+            }
+            variable_response::Status::WINDOW_NOT_FOUND => {
+                Err(Error::WindowNotFound(target.id().to_string()))
+            }
+            status => Err(Error::Api(format!("Variable request failed: {status:?}"))),
+        }
+    }
+}
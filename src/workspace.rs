@@ -0,0 +1,948 @@
+//! Declarative workspaces: windows, tabs and split panes described in a TOML or YAML file.
+//!
+//! A workspace lists windows, each window lists tabs, and each tab is a tree of panes. A pane is
+//! either a leaf that runs a shell (with an optional profile, working directory, title, user
+//! variables and startup commands) or a split that divides its space between child panes.
+//! Profile and working directory set on a split are inherited by its children.
+//!
+//! ```toml
+//! name = "backend"
+//!
+//! [[windows]]
+//! [[windows.tabs]]
+//! cwd = "~/src/api"
+//! split = "vertical"
+//!
+//! [[windows.tabs.panes]]
+//! title = "editor"
+//! commands = ["nvim ."]
+//! size = 2
+//!
+//! [[windows.tabs.panes]]
+//! split = "horizontal"
+//! panes = [
+//!     { title = "server", commands = ["cargo run"], variables = { role = "server" } },
+//!     { title = "shell" },
+//! ]
+//! ```
+//!
+//! `Workspace::plan()` turns a workspace into the sequence of requests that creates it, which
+//! can be printed for a dry run, and `ITerm2Connection::apply_workspace()` executes it. Going the
+//! other way, `ITerm2Connection::capture_workspace()` describes the windows that are open now.
+
+use crate::connection::ITerm2Connection;
+use crate::error::{Error, Result};
+use crate::generated::api::*;
+use crate::layout::{Axis, extent};
+use crate::variables::VariableTarget;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+
+/// A set of windows to create.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Workspace {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub windows: Vec<WindowSpec>,
+}
+
+/// A window and its tabs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WindowSpec {
+    /// The root pane of each tab, in order.
+    pub tabs: Vec<PaneSpec>,
+}
+
+/// A pane, or a split containing more panes.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PaneSpec {
+    /// Session name, shown as the pane and tab title. Leaves only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// Profile to create the pane with. Inherited by child panes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+
+    /// Initial working directory. `~` is expanded. Inherited by child panes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+
+    /// Commands to type into the pane once it exists. Leaves only.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub commands: Vec<String>,
+
+    /// User variables to set on the session. The `user.` prefix is added if missing. Leaves only.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, Value>,
+
+    /// Relative size within the parent split. Panes without a size count as 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<f64>,
+
+    /// Direction of the dividers between `panes`. Required if `panes` is not empty.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub split: Option<SplitDirection>,
+
+    /// Child panes. If empty this pane is a leaf.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub panes: Vec<PaneSpec>,
+}
+
+/// How a split divides its space.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitDirection {
+    /// Vertical dividers: panes side by side, left to right.
+    Vertical,
+    /// Horizontal dividers: panes stacked, top to bottom.
+    Horizontal,
+}
+
+impl SplitDirection {
+    fn is_vertical(self) -> bool {
+        self == SplitDirection::Vertical
+    }
+}
+
+/// Identifies a leaf pane of a workspace: window index, tab index and leaf index within the tab
+/// (depth-first, left-to-right and top-to-bottom).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PaneId {
+    pub window: usize,
+    pub tab: usize,
+    pub pane: usize,
+}
+
+impl fmt::Display for PaneId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "w{}.t{}.p{}", self.window, self.tab, self.pane)
+    }
+}
+
+/// One request in the plan that materializes a workspace.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// `CreateTabRequest` without a window ID, creating the window and its first pane.
+    CreateWindow {
+        pane: PaneId,
+        profile: Option<String>,
+        properties: Vec<(String, Value)>,
+    },
+    /// `CreateTabRequest` adding a tab to a window created earlier.
+    CreateTab {
+        pane: PaneId,
+        profile: Option<String>,
+        properties: Vec<(String, Value)>,
+    },
+    /// `SplitPaneRequest` splitting `source` to create `pane`.
+    SplitPane {
+        source: PaneId,
+        pane: PaneId,
+        direction: SplitDirection,
+        profile: Option<String>,
+        properties: Vec<(String, Value)>,
+    },
+    /// `SetTabLayoutRequest` applying relative sizes. Each entry is the link path of a split in
+    /// the tab's tree and the weights of its children.
+    SetTabLayout {
+        window: usize,
+        tab: usize,
+        proportions: Vec<(Vec<usize>, Vec<f64>)>,
+    },
+    /// `VariableRequest` setting a user variable on a pane's session.
+    SetVariable {
+        pane: PaneId,
+        name: String,
+        value: Value,
+    },
+    /// `SendTextRequest` typing a command into a pane.
+    SendText { pane: PaneId, text: String },
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn options(
+            f: &mut fmt::Formatter<'_>,
+            profile: &Option<String>,
+            properties: &[(String, Value)],
+        ) -> fmt::Result {
+            if let Some(profile) = profile {
+                write!(f, " profile={profile:?}")?;
+            }
+            for (key, value) in properties {
+                write!(f, " {key:?}={value}")?;
+            }
+            Ok(())
+        }
+
+        match self {
+            Step::CreateWindow {
+                pane,
+                profile,
+                properties,
+            } => {
+                write!(f, "CreateTabRequest new window -> {pane}")?;
+                options(f, profile, properties)
+            }
+            Step::CreateTab {
+                pane,
+                profile,
+                properties,
+            } => {
+                write!(f, "CreateTabRequest window w{} -> {pane}", pane.window)?;
+                options(f, profile, properties)
+            }
+            Step::SplitPane {
+                source,
+                pane,
+                direction,
+                profile,
+                properties,
+            } => {
+                write!(f, "SplitPaneRequest {source} {direction:?} -> {pane}")?;
+                options(f, profile, properties)
+            }
+            Step::SetTabLayout {
+                window,
+                tab,
+                proportions,
+            } => {
+                write!(f, "SetTabLayoutRequest w{window}.t{tab}")?;
+                for (path, weights) in proportions {
+                    write!(f, " {path:?}={weights:?}")?;
+                }
+                Ok(())
+            }
+            Step::SetVariable { pane, name, value } => {
+                write!(f, "VariableRequest {pane} {name}={value}")
+            }
+            Step::SendText { pane, text } => write!(f, "SendTextRequest {pane} {text:?}"),
+        }
+    }
+}
+
+/// The IDs of everything `apply_workspace()` created.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppliedWorkspace {
+    pub windows: Vec<AppliedWindow>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppliedWindow {
+    pub window_id: String,
+    pub tabs: Vec<AppliedTab>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppliedTab {
+    pub tab_id: String,
+    /// Session IDs of the tab's leaf panes, in `PaneId::pane` order.
+    pub session_ids: Vec<String>,
+}
+
+impl Workspace {
+    /// Parse a workspace from TOML.
+    ///
+    /// # Errors
+    /// Returns `Error::Workspace` if the document is not a valid workspace
+    pub fn from_toml_str(source: &str) -> Result<Self> {
+        toml::from_str(source).map_err(|e| Error::Workspace(e.to_string()))
+    }
+
+    /// Parse a workspace from YAML.
+    ///
+    /// # Errors
+    /// Returns `Error::Workspace` if the document is not a valid workspace
+    pub fn from_yaml_str(source: &str) -> Result<Self> {
+        serde_yaml::from_str(source).map_err(|e| Error::Workspace(e.to_string()))
+    }
+
+    /// Load a workspace file. The format is chosen by extension: `.toml`, `.yaml` or `.yml`.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the file can't be read, or `Error::Workspace` if it can't be parsed
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path)?;
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => Self::from_toml_str(&source),
+            Some("yaml" | "yml") => Self::from_yaml_str(&source),
+            _ => Err(Error::Workspace(format!(
+                "unsupported workspace file extension: {}",
+                path.display()
+            ))),
+        }
+    }
+
+    /// Serialize the workspace as TOML.
+    ///
+    /// # Errors
+    /// Returns `Error::Workspace` if a value can't be represented in TOML
+    ///
+    /// # Example
+    /// ```
+    /// use iterm2_api::workspace::Workspace;
+    ///
+    /// let workspace = Workspace::from_yaml_str(
+    ///     "
+    /// windows:
+    ///   - tabs:
+    ///       - split: horizontal
+    ///         panes:
+    ///           - { cwd: /var/log, commands: [tail -f system.log], size: 3 }
+    ///           - { variables: { role: shell } }
+    /// ",
+    /// )?;
+    ///
+    /// let toml = workspace.to_toml_string()?;
+    /// assert_eq!(Workspace::from_toml_str(&toml)?, workspace);
+    /// # Ok::<(), iterm2_api::Error>(())
+    /// ```
+    pub fn to_toml_string(&self) -> Result<String> {
+        toml::to_string_pretty(self).map_err(|e| Error::Workspace(e.to_string()))
+    }
+
+    /// Serialize the workspace as YAML.
+    ///
+    /// # Errors
+    /// Returns `Error::Workspace` if serialization fails
+    pub fn to_yaml_string(&self) -> Result<String> {
+        serde_yaml::to_string(self).map_err(|e| Error::Workspace(e.to_string()))
+    }
+
+    /// Write a workspace file. The format is chosen by extension, as in `load()`.
+    ///
+    /// # Errors
+    /// Returns `Error::Io` if the file can't be written, or `Error::Workspace` for an unknown
+    /// extension
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let contents = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("toml") => self.to_toml_string()?,
+            Some("yaml" | "yml") => self.to_yaml_string()?,
+            _ => {
+                return Err(Error::Workspace(format!(
+                    "unsupported workspace file extension: {}",
+                    path.display()
+                )));
+            }
+        };
+        std::fs::write(path, contents)?;
+        Ok(())
+    }
+
+    /// Check the workspace for mistakes that would make it impossible to create.
+    ///
+    /// # Errors
+    /// Returns `Error::Workspace` describing the first problem found
+    pub fn validate(&self) -> Result<()> {
+        if self.windows.is_empty() {
+            return Err(Error::Workspace("workspace has no windows".to_string()));
+        }
+        for (w, window) in self.windows.iter().enumerate() {
+            if window.tabs.is_empty() {
+                return Err(Error::Workspace(format!("window w{w} has no tabs")));
+            }
+            for (t, tab) in window.tabs.iter().enumerate() {
+                validate_pane(tab, None, &format!("w{w}.t{t}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Compute the requests that create this workspace, without sending anything.
+    ///
+    /// # Errors
+    /// Returns `Error::Workspace` if the workspace is invalid
+    ///
+    /// # Example
+    /// ```
+    /// use iterm2_api::workspace::Workspace;
+    ///
+    /// let workspace = Workspace::from_toml_str(r#"
+    ///     [[windows]]
+    ///     [[windows.tabs]]
+    ///     split = "vertical"
+    ///     panes = [{ commands = ["htop"] }, { cwd = "/tmp" }]
+    /// "#)?;
+    ///
+    /// for step in workspace.plan()? {
+    ///     println!("{step}");
+    /// }
+    /// // CreateTabRequest new window -> w0.t0.p0
+    /// // SplitPaneRequest w0.t0.p0 Vertical -> w0.t0.p1 "Custom Directory"="Yes" ...
+    /// // SendTextRequest w0.t0.p0 "htop\r"
+    /// assert_eq!(workspace.plan()?.len(), 3);
+    /// # Ok::<(), iterm2_api::Error>(())
+    /// ```
+    pub fn plan(&self) -> Result<Vec<Step>> {
+        self.validate()?;
+
+        let mut steps = Vec::new();
+        for (w, window) in self.windows.iter().enumerate() {
+            for (t, tab) in window.tabs.iter().enumerate() {
+                let mut planner = TabPlanner {
+                    window: w,
+                    tab: t,
+                    leaves: 0,
+                    creates: Vec::new(),
+                    proportions: Vec::new(),
+                    setup: Vec::new(),
+                };
+                let root = planner.next_leaf();
+                let (leaf, leaf_inherited) = first_leaf(tab, &Inherited::default());
+                let (profile, properties) = creation_options(leaf, &leaf_inherited);
+                planner.creates.push(if t == 0 {
+                    Step::CreateWindow {
+                        pane: root,
+                        profile,
+                        properties,
+                    }
+                } else {
+                    Step::CreateTab {
+                        pane: root,
+                        profile,
+                        properties,
+                    }
+                });
+                planner.expand(tab, root, &Inherited::default().with(tab), &mut Vec::new());
+
+                steps.append(&mut planner.creates);
+                if !planner.proportions.is_empty() {
+                    steps.push(Step::SetTabLayout {
+                        window: w,
+                        tab: t,
+                        proportions: planner.proportions,
+                    });
+                }
+                steps.append(&mut planner.setup);
+            }
+        }
+        Ok(steps)
+    }
+}
+
+impl ITerm2Connection {
+    /// Create the windows, tabs and panes described by a workspace.
+    ///
+    /// # Returns
+    /// The IDs of the created windows, tabs and sessions
+    ///
+    /// # Errors
+    /// Returns `Error::Workspace` if the workspace is invalid, or the error of the first request
+    /// that fails. Anything created before the failure is left open.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # use iterm2_api::workspace::Workspace;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let workspace = Workspace::load("backend.toml")?;
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let applied = connection.apply_workspace(&workspace).await?;
+    /// println!("Created {} windows", applied.windows.len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn apply_workspace(&mut self, workspace: &Workspace) -> Result<AppliedWorkspace> {
+        let steps = workspace.plan()?;
+
+        let mut applied = AppliedWorkspace::default();
+        let mut sessions: HashMap<PaneId, String> = HashMap::new();
+        let session = |sessions: &HashMap<PaneId, String>, pane: &PaneId| -> Result<String> {
+            sessions
+                .get(pane)
+                .cloned()
+                .ok_or_else(|| Error::Workspace(format!("pane {pane} was not created")))
+        };
+
+        for step in steps {
+            tracing::debug!("Workspace step: {step}");
+            match step {
+                Step::CreateWindow {
+                    pane,
+                    profile,
+                    properties,
+                }
+                | Step::CreateTab {
+                    pane,
+                    profile,
+                    properties,
+                } => {
+                    let mut request = CreateTabRequest::new();
+                    if let Some(profile) = profile {
+                        request.set_profile_name(profile);
+                    }
+                    if let Some(window) = applied.windows.get(pane.window) {
+                        request.set_window_id(window.window_id.clone());
+                    }
+                    request.custom_profile_properties = profile_properties(&properties);
+
+                    let response = self.create_tab_with(request).await?;
+                    if pane.window == applied.windows.len() {
+                        applied.windows.push(AppliedWindow {
+                            window_id: response.window_id().to_string(),
+                            tabs: Vec::new(),
+                        });
+                    }
+                    applied.windows[pane.window].tabs.push(AppliedTab {
+                        tab_id: response.tab_id().to_string(),
+                        session_ids: Vec::new(),
+                    });
+                    sessions.insert(pane, response.session_id().to_string());
+                }
+                Step::SplitPane {
+                    source,
+                    pane,
+                    direction,
+                    profile,
+                    properties,
+                } => {
+                    let mut request = SplitPaneRequest::new();
+                    request.set_session(session(&sessions, &source)?);
+                    request.set_split_direction(if direction.is_vertical() {
+                        split_pane_request::SplitDirection::VERTICAL
+                    } else {
+                        split_pane_request::SplitDirection::HORIZONTAL
+                    });
+                    if let Some(profile) = profile {
+                        request.set_profile_name(profile);
+                    }
+                    request.custom_profile_properties = profile_properties(&properties);
+
+                    let new_session = self
+                        .split_pane_with(request)
+                        .await?
+                        .into_iter()
+                        .next()
+                        .ok_or_else(|| Error::Api("Split pane returned no session".to_string()))?;
+                    sessions.insert(pane, new_session);
+                }
+                Step::SetTabLayout {
+                    window,
+                    tab,
+                    proportions,
+                } => {
+                    let tab_id = applied.windows[window].tabs[tab].tab_id.clone();
+                    let mut editor = self.edit_tab_layout(&tab_id).await?;
+                    for (path, weights) in &proportions {
+                        editor.set_proportions(path, weights)?;
+                    }
+                    self.set_tab_layout(&editor).await?;
+                }
+                Step::SetVariable { pane, name, value } => {
+                    let session_id = session(&sessions, &pane)?;
+                    self.set_variable(VariableTarget::Session(&session_id), &name, &value)
+                        .await?;
+                }
+                Step::SendText { pane, text } => {
+                    let session_id = session(&sessions, &pane)?;
+                    self.send_text(&session_id, &text).await?;
+                }
+            }
+        }
+
+        for (w, window) in applied.windows.iter_mut().enumerate() {
+            for (t, tab) in window.tabs.iter_mut().enumerate() {
+                let mut panes: Vec<(&PaneId, &String)> = sessions
+                    .iter()
+                    .filter(|(pane, _)| pane.window == w && pane.tab == t)
+                    .collect();
+                panes.sort_by_key(|(pane, _)| pane.pane);
+                tab.session_ids = panes.into_iter().map(|(_, id)| id.clone()).collect();
+            }
+        }
+        Ok(applied)
+    }
+
+    /// Describe the windows that are open now as a workspace.
+    ///
+    /// Each session's split position and relative size come from the layout; its working
+    /// directory, profile and user variables come from its variables. If a session is running
+    /// something other than a shell, the foreground command line is recorded as a startup
+    /// command. Minimized and buried sessions are not included.
+    ///
+    /// # Errors
+    /// Returns `Error::Api` if the layout or a session's variables can't be read
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// # use iterm2_api::ITerm2Connection;
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut connection = ITerm2Connection::connect().await?;
+    /// let workspace = connection.capture_workspace().await?;
+    /// workspace.save("today.toml")?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn capture_workspace(&mut self) -> Result<Workspace> {
+        let layout = self.get_layout().await?;
+        let mut workspace = Workspace::default();
+        for window in &layout.windows {
+            workspace
+                .windows
+                .push(self.capture_window_spec(window).await?);
+        }
+        Ok(workspace)
+    }
+
+    /// Describe a single window as a workspace. See `capture_workspace()`.
+    ///
+    /// # Errors
+    /// Returns `Error::WindowNotFound` if the window doesn't exist
+    pub async fn capture_window(&mut self, window_id: &str) -> Result<Workspace> {
+        let layout = self.get_layout().await?;
+        let window = crate::tree::find_window(&layout, window_id)
+            .ok_or_else(|| Error::WindowNotFound(window_id.to_string()))?;
+        Ok(Workspace {
+            name: None,
+            windows: vec![self.capture_window_spec(window).await?],
+        })
+    }
+
+    async fn capture_window_spec(
+        &mut self,
+        window: &list_sessions_response::Window,
+    ) -> Result<WindowSpec> {
+        let mut spec = WindowSpec::default();
+        for tab in &window.tabs {
+            let mut panes = HashMap::new();
+            for session in crate::tree::tab_sessions(tab) {
+                let pane = self.capture_pane(session).await?;
+                panes.insert(session.unique_identifier().to_string(), pane);
+            }
+            spec.tabs.push(pane_from_node(&tab.root, &mut panes));
+        }
+        Ok(spec)
+    }
+
+    async fn capture_pane(&mut self, session: &SessionSummary) -> Result<PaneSpec> {
+        let variables = self
+            .get_all_variables(VariableTarget::Session(session.unique_identifier()))
+            .await?;
+        let string = |name: &str| {
+            variables
+                .get(name)
+                .and_then(Value::as_str)
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        };
+
+        let mut pane = PaneSpec {
+            title: Some(session.title().to_string()).filter(|title| !title.is_empty()),
+            profile: string("profileName").filter(|profile| profile != "Default"),
+            cwd: string("path"),
+            ..PaneSpec::default()
+        };
+
+        if let Some(job) = string("jobName")
+            && !is_shell(&job)
+        {
+            pane.commands.push(string("commandLine").unwrap_or(job));
+        }
+
+        if let Some(Value::Object(user)) = variables.get("user") {
+            pane.variables = user
+                .iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect();
+        }
+        Ok(pane)
+    }
+}
+
+/// Settings inherited from enclosing splits.
+#[derive(Debug, Clone, Default)]
+struct Inherited {
+    profile: Option<String>,
+    cwd: Option<String>,
+}
+
+impl Inherited {
+    fn with(&self, pane: &PaneSpec) -> Self {
+        Self {
+            profile: pane.profile.clone().or_else(|| self.profile.clone()),
+            cwd: pane.cwd.clone().or_else(|| self.cwd.clone()),
+        }
+    }
+}
+
+struct TabPlanner {
+    window: usize,
+    tab: usize,
+    leaves: usize,
+    /// Requests creating the tab and its panes.
+    creates: Vec<Step>,
+    /// Relative sizes, applied once all panes exist.
+    proportions: Vec<(Vec<usize>, Vec<f64>)>,
+    /// Variables and commands, sent after the layout is final.
+    setup: Vec<Step>,
+}
+
+impl TabPlanner {
+    fn next_leaf(&mut self) -> PaneId {
+        let pane = PaneId {
+            window: self.window,
+            tab: self.tab,
+            pane: self.leaves,
+        };
+        self.leaves += 1;
+        pane
+    }
+
+    /// Plan the subtree of `spec`, whose area is currently occupied by the session `pane`.
+    ///
+    /// All children of a split are created before any of them is split further, so that each
+    /// split only ever divides a pane that is still a leaf. `pane` becomes the first leaf of
+    /// the first child and each new sibling is split off the first leaf of the previous one.
+    fn expand(
+        &mut self,
+        spec: &PaneSpec,
+        pane: PaneId,
+        inherited: &Inherited,
+        path: &mut Vec<usize>,
+    ) {
+        let Some(direction) = spec.split else {
+            self.leaf_setup(spec, pane);
+            return;
+        };
+
+        let mut firsts = vec![pane];
+        for child in &spec.panes[1..] {
+            let source = *firsts.last().unwrap_or(&pane);
+            let new_pane = self.next_leaf();
+            let (leaf, leaf_inherited) = first_leaf(child, inherited);
+            let (profile, properties) = creation_options(leaf, &leaf_inherited);
+            self.creates.push(Step::SplitPane {
+                source,
+                pane: new_pane,
+                direction,
+                profile,
+                properties,
+            });
+            firsts.push(new_pane);
+        }
+
+        // Sequential splits halve the pane being split, so three or more panes only come out
+        // even if their sizes are set afterwards.
+        if spec.panes.len() > 2 || spec.panes.iter().any(|child| child.size.is_some()) {
+            let weights = spec
+                .panes
+                .iter()
+                .map(|child| child.size.unwrap_or(1.0))
+                .collect();
+            self.proportions.push((path.clone(), weights));
+        }
+
+        for (i, (child, first)) in spec.panes.iter().zip(firsts).enumerate() {
+            path.push(i);
+            self.expand(child, first, &inherited.with(child), path);
+            path.pop();
+        }
+    }
+
+    fn leaf_setup(&mut self, spec: &PaneSpec, pane: PaneId) {
+        for (name, value) in &spec.variables {
+            let name = if name.starts_with("user.") {
+                name.clone()
+            } else {
+                format!("user.{name}")
+            };
+            self.setup.push(Step::SetVariable {
+                pane,
+                name,
+                value: value.clone(),
+            });
+        }
+        for command in &spec.commands {
+            self.setup.push(Step::SendText {
+                pane,
+                text: format!("{command}\r"),
+            });
+        }
+    }
+}
+
+/// The first leaf of a subtree, which is where the session created for the subtree ends up,
+/// along with the settings it inherits.
+fn first_leaf<'a>(spec: &'a PaneSpec, inherited: &Inherited) -> (&'a PaneSpec, Inherited) {
+    let mut spec = spec;
+    let mut inherited = inherited.with(spec);
+    while let Some(child) = spec.panes.first() {
+        inherited = inherited.with(child);
+        spec = child;
+    }
+    (spec, inherited)
+}
+
+/// The profile and custom profile properties used to create a leaf pane.
+fn creation_options(
+    spec: &PaneSpec,
+    inherited: &Inherited,
+) -> (Option<String>, Vec<(String, Value)>) {
+    let mut properties = Vec::new();
+    if let Some(cwd) = &inherited.cwd {
+        properties.push(("Custom Directory".to_string(), Value::from("Yes")));
+        properties.push((
+            "Working Directory".to_string(),
+            Value::from(expand_home(cwd)),
+        ));
+    }
+    if let Some(title) = &spec.title {
+        properties.push(("Name".to_string(), Value::from(title.as_str())));
+    }
+    (inherited.profile.clone(), properties)
+}
+
+fn profile_properties(properties: &[(String, Value)]) -> Vec<ProfileProperty> {
+    properties
+        .iter()
+        .map(|(key, value)| {
+            let mut property = ProfileProperty::new();
+            property.set_key(key.clone());
+            property.set_json_value(value.to_string());
+            property
+        })
+        .collect()
+}
+
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{rest}", home.display())
+        }
+        _ => path.to_string(),
+    }
+}
+
+fn validate_pane(pane: &PaneSpec, parent: Option<SplitDirection>, path: &str) -> Result<()> {
+    let invalid = |reason: &str| Err(Error::Workspace(format!("pane {path}: {reason}")));
+
+    if let Some(size) = pane.size
+        && !(size.is_finite() && size > 0.0)
+    {
+        return invalid("size must be a positive number");
+    }
+
+    match pane.split {
+        None if !pane.panes.is_empty() => invalid("`panes` requires `split`"),
+        None => Ok(()),
+        Some(_) if pane.panes.len() < 2 => invalid("a split needs at least two panes"),
+        Some(direction) if parent == Some(direction) => {
+            invalid("nested splits must alternate direction")
+        }
+        Some(_) if pane.title.is_some() || !pane.commands.is_empty() => {
+            invalid("only leaf panes can have a title or commands")
+        }
+        Some(_) if !pane.variables.is_empty() => invalid("only leaf panes can have variables"),
+        Some(direction) => {
+            for (i, child) in pane.panes.iter().enumerate() {
+                validate_pane(child, Some(direction), &format!("{path}/{i}"))?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Convert a live split tree into a pane spec, taking the captured leaves from `panes`.
+fn pane_from_node(node: &SplitTreeNode, panes: &mut HashMap<String, PaneSpec>) -> PaneSpec {
+    use crate::generated::api::split_tree_node::split_tree_link::Child;
+
+    let direction = if node.vertical() {
+        SplitDirection::Vertical
+    } else {
+        SplitDirection::Horizontal
+    };
+
+    let axis = Axis::of(node);
+    let mut children = Vec::new();
+    let mut extents = Vec::new();
+    for link in &node.links {
+        match &link.child {
+            Some(Child::Session(session)) => children.push(
+                panes
+                    .remove(session.unique_identifier())
+                    .unwrap_or_default(),
+            ),
+            Some(Child::Node(child)) => children.push(pane_from_node(child, panes)),
+            None => continue,
+        }
+        extents.push(extent(link.child.as_ref(), axis));
+    }
+
+    if children.len() == 1 {
+        return children.pop().unwrap_or_default();
+    }
+
+    // Only record sizes when the panes aren't already evenly split.
+    if extents.iter().all(|extent| *extent > 0) && extents.windows(2).any(|w| w[0] != w[1]) {
+        for (child, extent) in children.iter_mut().zip(&extents) {
+            child.size = Some(f64::from(*extent));
+        }
+    }
+
+    PaneSpec {
+        split: Some(direction),
+        panes: children,
+        ..PaneSpec::default()
+    }
+}
+
+fn is_shell(job_name: &str) -> bool {
+    const SHELLS: &[&str] = &[
+        "bash", "zsh", "fish", "sh", "dash", "ksh", "tcsh", "csh", "nu", "xonsh", "login",
+    ];
+    let name = job_name.trim_start_matches('-');
+    let name = name.rsplit('/').next().unwrap_or(name);
+    SHELLS.contains(&name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layouts(source: &str) -> Vec<Vec<(Vec<usize>, Vec<f64>)>> {
+        Workspace::from_toml_str(source)
+            .unwrap()
+            .plan()
+            .unwrap()
+            .into_iter()
+            .filter_map(|step| match step {
+                Step::SetTabLayout { proportions, .. } => Some(proportions),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn three_panes_without_sizes_are_evened_out() {
+        let source = r#"
+            [[windows]]
+            [[windows.tabs]]
+            split = "vertical"
+            panes = [{}, {}, {}]
+        "#;
+        assert_eq!(layouts(source), vec![vec![(vec![], vec![1.0; 3])]]);
+    }
+
+    #[test]
+    fn two_panes_without_sizes_need_no_layout() {
+        let source = r#"
+            [[windows]]
+            [[windows.tabs]]
+            split = "vertical"
+            panes = [{}, { split = "horizontal", panes = [{ size = 3 }, {}] }]
+        "#;
+        assert_eq!(layouts(source), vec![vec![(vec![1], vec![3.0, 1.0])]]);
+    }
+}